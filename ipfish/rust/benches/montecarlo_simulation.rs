@@ -1,8 +1,10 @@
 use divan::{Bencher, black_box};
 use ipfish::RNG;
+use ipfish::bitboard::BitBoard;
 use ipfish::board::{Board, BoardHistory};
 use ipfish::montecarlo_score::montecarlo_simulation;
 use ipfish::player::Player;
+use ipfish::zobrist::zobrist_hash;
 use rand::SeedableRng;
 use rustc_hash::FxBuildHasher;
 use std::collections::HashSet;
@@ -17,17 +19,26 @@ fn main() {
 #[divan::bench]
 fn bench_montecarlo_score(bencher: Bencher) {
     let empty_fivebyfive_board: Box<[u8]> = vec![1; 25].into_boxed_slice();
-    let mut board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher::default());
-    board_history.insert(empty_fivebyfive_board.clone());
+    let mut board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+    board_history.insert(zobrist_hash(&empty_fivebyfive_board));
     let mut rng: RNG = RNG::seed_from_u64(black_box(42));
 
-    let board: Board = Board {
-        board: empty_fivebyfive_board,
-        komi: 5.5,
-        size: 5,
-        player: Player::Black,
-        opponent_passed: false,
-    };
+    let board: Board = Board::new(empty_fivebyfive_board, 5, Player::Black, 5.5, false);
+
+    bencher.bench_local(|| {
+        montecarlo_simulation(board.clone(), black_box(board_history.clone()), black_box(&mut rng));
+    });
+}
+
+/// The same simulation as above, but on the bitboard backend.
+#[divan::bench]
+fn bench_montecarlo_score_bitboard(bencher: Bencher) {
+    let empty_fivebyfive_board: Box<[u8]> = vec![1; 25].into_boxed_slice();
+    let mut board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+    board_history.insert(zobrist_hash(&empty_fivebyfive_board));
+    let mut rng: RNG = RNG::seed_from_u64(black_box(42));
+
+    let board: BitBoard = BitBoard::from(&Board::new(empty_fivebyfive_board, 5, Player::Black, 5.5, false));
 
     bencher.bench_local(|| {
         montecarlo_simulation(board.clone(), black_box(board_history.clone()), black_box(&mut rng));
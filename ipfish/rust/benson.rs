@@ -0,0 +1,316 @@
+use crate::bitset::BitSet;
+use crate::board::Board;
+use crate::get_adjacent_points::get_adjacent_points;
+use crate::player::Player;
+use crate::point_state::PointState;
+
+/// A connected group of points, found with a flood fill.
+struct Area {
+    points: BitSet,
+    // For chains the liberties, for regions the empty points.
+    empty: BitSet,
+    // The indexes of the areas of the other kind that border this one.
+    neighbours: Vec<usize>,
+}
+
+/// What Benson's algorithm found for one player.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Benson {
+    /// The chains of the player that can't be captured, even if the opponent gets as many moves in a row as they like.
+    pub alive_chains: Vec<BitSet>,
+    /// The regions enclosed by the alive chains in which the opponent can't make an eye, nor capture anything.
+    /// A region is a connected group of points that are empty or hold opponent stones. Opponent stones in a safe region are dead.
+    pub safe_regions: Vec<BitSet>,
+}
+
+/// The points where playing is pointless for each player, because the outcome there is settled already.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SettledPoints {
+    pub black: BitSet,
+    pub white: BitSet,
+}
+
+impl SettledPoints {
+    /// The points where playing is pointless for `player`.
+    pub fn for_player(&self, player: Player) -> BitSet {
+        return match player {
+            Player::Black => self.black,
+            Player::White => self.white,
+        };
+    }
+}
+
+/// Runs Benson's algorithm for unconditional life for the player.
+/// A region is safe when every chain of the player around it is unconditionally alive, and every empty point in it is a liberty
+/// of one of those chains. Offline points are neither chains nor regions, they're like the edge of the board.
+///
+/// # Arguments
+///
+/// * `board` - The board to run the algorithm on.
+/// * `player` - The player whose chains and regions to find.
+pub fn benson(board: &Board, player: Player) -> Benson {
+    let (mut chains, chain_of): (Vec<Area>, Vec<Option<usize>>) = find_areas(board, |state| state == player as u8);
+    let (mut regions, _): (Vec<Area>, Vec<Option<usize>>) = find_areas(board, |state| state == PointState::Empty as u8 || state == !player as u8);
+    for (region_index, region) in regions.iter_mut().enumerate() {
+        for point in region.points {
+            for adjacent_point in get_adjacent_points(point, board) {
+                if let Some(chain_index) = chain_of[adjacent_point] {
+                    if !region.neighbours.contains(&chain_index) {
+                        region.neighbours.push(chain_index);
+                        chains[chain_index].neighbours.push(region_index);
+                    }
+                }
+            }
+        }
+    }
+
+    // A region is vital to a chain when all its empty points are liberties of the chain.
+    // The opponent can't fill a vital region without capturing themselves first, so a chain with two of them can't be captured.
+    let vital: Vec<Vec<usize>> = chains
+        .iter()
+        .map(|chain| {
+            chain
+                .neighbours
+                .iter()
+                .copied()
+                .filter(|region_index| (regions[*region_index].empty & !chain.empty).empty())
+                .collect()
+        })
+        .collect();
+
+    // Take out the chains with less than two vital regions, and the regions bordering chains that were taken out, until nothing changes.
+    let mut alive_chains: Vec<bool> = vec![true; chains.len()];
+    let mut enclosed_regions: Vec<bool> = vec![true; regions.len()];
+    let mut changed: bool = true;
+    while changed {
+        changed = false;
+        for chain_index in 0..chains.len() {
+            if alive_chains[chain_index] && vital[chain_index].iter().filter(|region_index| enclosed_regions[**region_index]).count() < 2 {
+                alive_chains[chain_index] = false;
+                changed = true;
+            }
+        }
+        for region_index in 0..regions.len() {
+            if enclosed_regions[region_index] && regions[region_index].neighbours.iter().any(|chain_index| !alive_chains[*chain_index]) {
+                enclosed_regions[region_index] = false;
+                changed = true;
+            }
+        }
+    }
+
+    let mut alive_liberties: BitSet = BitSet::new();
+    for (chain_index, chain) in chains.iter().enumerate() {
+        if alive_chains[chain_index] {
+            alive_liberties |= chain.empty;
+        }
+    }
+    return Benson {
+        alive_chains: chains
+            .into_iter()
+            .enumerate()
+            .filter(|(chain_index, _)| alive_chains[*chain_index])
+            .map(|(_, chain)| chain.points)
+            .collect(),
+        safe_regions: regions
+            .into_iter()
+            .enumerate()
+            .filter(|(region_index, region)| enclosed_regions[*region_index] && !region.neighbours.is_empty() && (region.empty & !alive_liberties).empty())
+            .map(|(_, region)| region.points)
+            .collect(),
+    };
+}
+
+/// Finds the points where playing is pointless for either player, going by Benson's algorithm.
+/// Stones in the opponent's safe regions can't live, and can't capture anything. A player's own safe regions are theirs already,
+/// unless the opponent has dead stones in there, which still have to be captured for them to count.
+///
+/// # Arguments
+///
+/// * `board` - The board to find the settled points of.
+pub fn settled_points(board: &Board) -> SettledPoints {
+    let black: Benson = benson(board, Player::Black);
+    let white: Benson = benson(board, Player::White);
+    return SettledPoints {
+        black: pointless_for(board, &black, &white),
+        white: pointless_for(board, &white, &black),
+    };
+}
+
+/// The opponent's safe regions, and the safe regions of our own that are empty.
+fn pointless_for(board: &Board, own: &Benson, opponent: &Benson) -> BitSet {
+    let mut result: BitSet = BitSet::new();
+    for region in opponent.safe_regions.iter() {
+        result |= *region;
+    }
+    for region in own.safe_regions.iter() {
+        if region.into_iter().all(|point| board.board[point] == PointState::Empty as u8) {
+            result |= *region;
+        }
+    }
+    return result;
+}
+
+/// Flood fills the board into connected areas of the points whose state is in the area.
+/// Returns the areas, and the index of the area every point is in. Their neighbours are left for the caller to link up.
+fn find_areas(board: &Board, in_area: impl Fn(u8) -> bool) -> (Vec<Area>, Vec<Option<usize>>) {
+    let mut area_of: Vec<Option<usize>> = vec![None; board.board.len()];
+    let mut areas: Vec<Area> = Vec::new();
+    for start in 0..board.board.len() {
+        if area_of[start].is_some() || !in_area(board.board[start]) {
+            continue;
+        }
+        let mut area: Area = Area {
+            points: BitSet::new(),
+            empty: BitSet::new(),
+            neighbours: Vec::new(),
+        };
+        let mut stack: Vec<usize> = vec![start];
+        area_of[start] = Some(areas.len());
+        while let Some(point) = stack.pop() {
+            area.points.insert(point);
+            if board.board[point] == PointState::Empty as u8 {
+                area.empty.insert(point);
+            }
+            for adjacent_point in get_adjacent_points(point, board) {
+                let state: u8 = board.board[adjacent_point];
+                if in_area(state) {
+                    if area_of[adjacent_point].is_none() {
+                        area_of[adjacent_point] = Some(areas.len());
+                        stack.push(adjacent_point);
+                    }
+                } else if state == PointState::Empty as u8 {
+                    // A liberty of a chain.
+                    area.empty.insert(adjacent_point);
+                }
+            }
+        }
+        areas.push(area);
+    }
+    return (areas, area_of);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board_from_string::board_from_string;
+
+    fn board(board_string: &str, player: Player) -> Board {
+        return Board::new(board_from_string(board_string, 5), 5, player, 5.5, false);
+    }
+
+    #[test]
+    fn two_eyes_are_safe() {
+        let black_board: Board = board(
+            "
+    .X.X.
+    XXXXX
+    .....
+    .....
+    .....
+    ",
+            Player::Black,
+        );
+        // The big region has points the opponent can make eyes with, and the other player has nothing alive.
+        assert_eq!(
+            benson(&black_board, Player::Black).safe_regions.into_iter().flatten().collect::<Vec<usize>>(),
+            vec![20, 22, 24]
+        );
+        assert_eq!(
+            benson(&black_board, Player::Black).alive_chains.into_iter().flatten().collect::<Vec<usize>>(),
+            vec![15, 16, 17, 18, 19, 21, 23]
+        );
+        assert!(benson(&black_board, Player::White).safe_regions.is_empty());
+
+        let white_board: Board = board(
+            "
+    .O.O.
+    OOOOO
+    .....
+    .....
+    .....
+    ",
+            Player::White,
+        );
+        assert_eq!(
+            benson(&white_board, Player::White).safe_regions.into_iter().flatten().collect::<Vec<usize>>(),
+            vec![20, 22, 24]
+        );
+        assert!(benson(&white_board, Player::Black).safe_regions.is_empty());
+    }
+
+    #[test]
+    fn one_eye_is_not_safe() {
+        let board: Board = board(
+            "
+    ..X..
+    XXX..
+    .....
+    .....
+    .....
+    ",
+            Player::Black,
+        );
+        assert!(benson(&board, Player::Black).safe_regions.is_empty());
+        assert!(benson(&board, Player::Black).alive_chains.is_empty());
+    }
+
+    #[test]
+    fn dead_stones_are_in_safe_regions() {
+        let board: Board = board(
+            "
+    .XO..
+    XXXXX
+    OOOOO
+    O.O.O
+    OOOOO
+    ",
+            Player::Black,
+        );
+        let black_regions: Vec<BitSet> = benson(&board, Player::Black).safe_regions;
+        // The white stone in the eye on the right still has a liberty, but black has two eyes and can always take it.
+        assert_eq!(black_regions.len(), 2);
+        assert!(black_regions.iter().any(|region| region.contains(22)));
+        // White is alive as well, and so is its territory.
+        let white_regions: Vec<BitSet> = benson(&board, Player::White).safe_regions;
+        assert_eq!(white_regions.into_iter().flatten().collect::<Vec<usize>>(), vec![6, 8]);
+    }
+
+    #[test]
+    fn offline_points_are_like_the_edge() {
+        let board: Board = board(
+            "
+    .X#X.
+    XXXXX
+    .....
+    .....
+    .....
+    ",
+            Player::Black,
+        );
+        assert_eq!(
+            benson(&board, Player::Black).safe_regions.into_iter().flatten().collect::<Vec<usize>>(),
+            vec![20, 24]
+        );
+    }
+
+    #[test]
+    fn settled_points_are_pointless_to_play() {
+        let board: Board = board(
+            "
+    .XO..
+    XXXXX
+    OOOOO
+    O.O.O
+    OOOOO
+    ",
+            Player::Black,
+        );
+        let settled: SettledPoints = settled_points(&board);
+        // Black still has to capture the dead white stone to score its eye, white can't do anything there.
+        assert_eq!(settled.for_player(Player::Black).into_iter().collect::<Vec<usize>>(), vec![6, 8, 20]);
+        assert_eq!(
+            settled.for_player(Player::White).into_iter().collect::<Vec<usize>>(),
+            vec![6, 8, 20, 22, 23, 24]
+        );
+    }
+}
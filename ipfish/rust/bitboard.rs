@@ -0,0 +1,350 @@
+use crate::benson::{SettledPoints, settled_points};
+use crate::bitset::BitSet;
+use crate::board::{Board, BoardHistory};
+use crate::player::Player;
+use crate::point_state::PointState;
+use crate::playout_board::PlayoutBoard;
+use crate::zobrist::zobrist_key;
+
+/// A board stored as one bitset per point state.
+/// Liberties, captures and eyes are computed for many points at once by shifting whole bitsets around,
+/// rather than by walking the adjacent points of each point. This makes it a lot faster for monte carlo playouts.
+#[derive(Clone, Debug)]
+pub struct BitBoard {
+    pub black: BitSet,
+    pub white: BitSet,
+    pub empty: BitSet,
+    pub offline: BitSet,
+    // The size of the board (the square root of the number of points on it).
+    pub size: usize,
+    // Who's turn it currently is to play.
+    pub player: Player,
+    // The bonus points that white gets to their score.
+    pub komi: f64,
+    // True if the opponent passed last turn, false otherwise.
+    pub opponent_passed: bool,
+    // The zobrist hash of the pieces on the board, the same as the hash of the equivalent Board.
+    pub hash: u64,
+    // Every point on the board.
+    on_board: BitSet,
+    // Every point on the board that isn't in the leftmost column.
+    not_first_column: BitSet,
+    // Every point on the board that isn't in the rightmost column.
+    not_last_column: BitSet,
+}
+
+impl From<&Board> for BitBoard {
+    fn from(board: &Board) -> BitBoard {
+        let mut result: BitBoard = BitBoard {
+            black: BitSet::new(),
+            white: BitSet::new(),
+            empty: BitSet::new(),
+            offline: BitSet::new(),
+            size: board.size,
+            player: board.player,
+            komi: board.komi,
+            opponent_passed: board.opponent_passed,
+            hash: board.hash,
+            on_board: BitSet::new(),
+            not_first_column: BitSet::new(),
+            not_last_column: BitSet::new(),
+        };
+        for point in 0..board.board.len() {
+            result.on_board.insert(point);
+            if point % board.size != 0 {
+                result.not_first_column.insert(point);
+            }
+            if point % board.size != board.size - 1 {
+                result.not_last_column.insert(point);
+            }
+
+            if board.board[point] == PointState::Black as u8 {
+                result.black.insert(point);
+            } else if board.board[point] == PointState::White as u8 {
+                result.white.insert(point);
+            } else if board.board[point] == PointState::Empty as u8 {
+                result.empty.insert(point);
+            } else {
+                result.offline.insert(point);
+            }
+        }
+        return result;
+    }
+}
+
+impl From<&BitBoard> for Board {
+    fn from(bitboard: &BitBoard) -> Board {
+        let mut points: Vec<u8> = vec![PointState::Offline as u8; bitboard.size * bitboard.size];
+        for point in bitboard.black {
+            points[point] = PointState::Black as u8;
+        }
+        for point in bitboard.white {
+            points[point] = PointState::White as u8;
+        }
+        for point in bitboard.empty {
+            points[point] = PointState::Empty as u8;
+        }
+        return Board::new(points.into_boxed_slice(), bitboard.size, bitboard.player, bitboard.komi, bitboard.opponent_passed);
+    }
+}
+
+impl BitBoard {
+    /// Returns every point that is adjacent to a point in `points`.
+    #[inline]
+    pub fn neighbours(&self, points: BitSet) -> BitSet {
+        let vertical: BitSet = (points << self.size) | (points >> self.size);
+        let horizontal: BitSet = ((points & self.not_last_column) << 1) | ((points & self.not_first_column) >> 1);
+        return (vertical | horizontal) & self.on_board;
+    }
+
+    /// Grows `seed` into every point of `within` that is connected to it.
+    /// With a single stone as the seed and stones of its colour as `within`, this is the stone's chain.
+    #[inline]
+    pub fn flood(&self, seed: BitSet, within: BitSet) -> BitSet {
+        let mut result: BitSet = seed & within;
+        loop {
+            let grown: BitSet = (result | self.neighbours(result)) & within;
+            if grown == result {
+                return result;
+            }
+            result = grown;
+        }
+    }
+
+    /// Returns the empty points adjacent to any of `stones`.
+    #[inline]
+    pub fn liberties(&self, stones: BitSet) -> BitSet {
+        return self.neighbours(stones) & self.empty;
+    }
+
+    #[inline]
+    fn stones(&self, player: Player) -> BitSet {
+        match player {
+            Player::Black => self.black,
+            Player::White => self.white,
+        }
+    }
+
+    fn stones_mut(&mut self, player: Player) -> &mut BitSet {
+        match player {
+            Player::Black => &mut self.black,
+            Player::White => &mut self.white,
+        }
+    }
+
+    /// Splits the stones of a player into their chains, and classifies them by liberty count.
+    /// Returns the stones of chains with 2 or more liberties, and the liberty of every chain in atari.
+    fn chains_by_liberties(&self, player: Player) -> (BitSet, BitSet) {
+        let mut remaining: BitSet = self.stones(player);
+        let mut safe_stones: BitSet = BitSet::new();
+        let mut atari_liberties: BitSet = BitSet::new();
+        while let Some(stone) = remaining.first() {
+            let mut seed: BitSet = BitSet::new();
+            seed.insert(stone);
+            let chain: BitSet = self.flood(seed, remaining);
+            remaining &= !chain;
+
+            let liberties: BitSet = self.liberties(chain);
+            if liberties.len() >= 2 {
+                safe_stones |= chain;
+            } else {
+                atari_liberties |= liberties;
+            }
+        }
+        return (safe_stones, atari_liberties);
+    }
+
+    /// Returns the enemy stones that would be captured by `player` playing at `point`.
+    fn captured_by(&self, point: usize, player: Player) -> BitSet {
+        let mut played: BitSet = BitSet::new();
+        played.insert(point);
+        let mut result: BitSet = BitSet::new();
+        for adjacent_stone in self.neighbours(played) & self.stones(!player) {
+            if result.contains(adjacent_stone) {
+                continue;
+            }
+            let mut seed: BitSet = BitSet::new();
+            seed.insert(adjacent_stone);
+            let chain: BitSet = self.flood(seed, self.stones(!player));
+            if (self.liberties(chain) & !played).empty() {
+                result |= chain;
+            }
+        }
+        return result;
+    }
+
+    fn hash_after_move(&self, point: usize, player: Player, captured: BitSet) -> u64 {
+        let mut hash: u64 = self.hash ^ zobrist_key(point, player as u8);
+        for captured_point in captured {
+            hash ^= zobrist_key(captured_point, !player as u8);
+        }
+        return hash;
+    }
+
+    /// Returns a bitset where the 1 bits are legal moves for `player`.
+    /// Like get_legal_moves_strict if `strict` is true, and like get_legal_moves otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `player` - The player to get the legal moves of, not necessarily the one who's turn it is.
+    /// * `friendly_safe_stones` - The stones of `player` in chains with 2 or more liberties, from chains_by_liberties.
+    /// * `enemy_atari_liberties` - The liberties of the enemy chains in atari, from chains_by_liberties.
+    /// * `board_history` - All states the board has historically been in, for superko.
+    /// * `strict` - Whether to check superko for moves that don't capture.
+    fn legal_moves_for(&self, player: Player, friendly_safe_stones: BitSet, enemy_atari_liberties: BitSet, board_history: &BoardHistory, strict: bool) -> BitSet {
+        // Captures are always legal, unless they violate superko.
+        let capturing_moves: BitSet = self.empty & enemy_atari_liberties;
+        // Otherwise a move needs an empty neighbour, or a neighbouring friendly chain with a liberty to spare.
+        let breathing_moves: BitSet = self.empty & self.neighbours(self.empty | friendly_safe_stones) & !capturing_moves;
+
+        let mut result: BitSet = BitSet::new();
+        for point in capturing_moves {
+            if !board_history.contains(&self.hash_after_move(point, player, self.captured_by(point, player))) {
+                result.insert(point);
+            }
+        }
+        if strict {
+            for point in breathing_moves {
+                if !board_history.contains(&self.hash_after_move(point, player, BitSet::new())) {
+                    result.insert(point);
+                }
+            }
+        } else {
+            result |= breathing_moves;
+        }
+        return result;
+    }
+
+    /// Returns a bitset where the 1 bits are legal moves. This version checks for superko even for non captures.
+    pub fn legal_moves_strict(&self, board_history: &BoardHistory) -> BitSet {
+        let (friendly_safe_stones, _) = self.chains_by_liberties(self.player);
+        let (_, enemy_atari_liberties) = self.chains_by_liberties(!self.player);
+        return self.legal_moves_for(self.player, friendly_safe_stones, enemy_atari_liberties, board_history, true);
+    }
+
+    /// Returns the score from stones and territory, owned by whichever player surrounds a whole empty region alone.
+    fn area_score(&self) -> f64 {
+        return self.area(Player::Black).len() as f64 - self.area(Player::White).len() as f64;
+    }
+}
+
+impl PlayoutBoard for BitBoard {
+    fn player(&self) -> Player {
+        return self.player;
+    }
+    fn opponent_passed(&self) -> bool {
+        return self.opponent_passed;
+    }
+    fn point_count(&self) -> usize {
+        return self.size * self.size;
+    }
+    fn hash(&self) -> u64 {
+        return self.hash;
+    }
+    fn legal_moves(&self, board_history: &BoardHistory) -> BitSet {
+        let (friendly_safe_stones, _) = self.chains_by_liberties(self.player);
+        let (_, enemy_atari_liberties) = self.chains_by_liberties(!self.player);
+        return self.legal_moves_for(self.player, friendly_safe_stones, enemy_atari_liberties, board_history, false);
+    }
+    fn true_eyes(&self) -> BitSet {
+        return self.empty & !self.neighbours(self.empty | self.stones(!self.player));
+    }
+    // Overridden so the chains only get split up once for both players.
+    fn playout_moves(&self, board_history: &BoardHistory) -> BitSet {
+        let (friendly_safe_stones, friendly_atari_liberties) = self.chains_by_liberties(self.player);
+        let (enemy_safe_stones, enemy_atari_liberties) = self.chains_by_liberties(!self.player);
+        let legal_moves: BitSet = self.legal_moves_for(self.player, friendly_safe_stones, enemy_atari_liberties, board_history, false);
+        let legal_for_opponent: BitSet = self.legal_moves_for(!self.player, enemy_safe_stones, friendly_atari_liberties, board_history, false);
+        return legal_moves & (!self.true_eyes() | legal_for_opponent);
+    }
+    fn play(&self, point: usize) -> Self {
+        let mut result: BitBoard = self.clone();
+        result.stones_mut(self.player).insert(point);
+        result.empty.remove(point);
+
+        // Every enemy chain that still touches an empty point survives, all of them are found with a single flood.
+        let enemy_stones: BitSet = result.stones(!self.player);
+        let captured: BitSet = enemy_stones & !result.flood(enemy_stones & result.neighbours(result.empty), enemy_stones);
+        *result.stones_mut(!self.player) &= !captured;
+        result.empty |= captured;
+        result.hash = self.hash_after_move(point, self.player, captured);
+        result.player = !self.player;
+        result.opponent_passed = false;
+        return result;
+    }
+    fn pass(&self) -> Self {
+        let mut result: BitBoard = self.clone();
+        result.player = !self.player;
+        result.opponent_passed = true;
+        return result;
+    }
+    fn settled_points(&self) -> SettledPoints {
+        return settled_points(&Board::from(self));
+    }
+    fn area(&self, player: Player) -> BitSet {
+        let mut result: BitSet = self.stones(player);
+        let mut remaining: BitSet = self.empty;
+        while let Some(point) = remaining.first() {
+            let mut seed: BitSet = BitSet::new();
+            seed.insert(point);
+            let region: BitSet = self.flood(seed, self.empty);
+            remaining &= !region;
+
+            let border: BitSet = self.neighbours(region);
+            if !(border & self.stones(player)).empty() && (border & self.stones(!player)).empty() {
+                result |= region;
+            }
+        }
+        return result;
+    }
+    fn score(&self) -> f64 {
+        return self.area_score() - self.komi;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RNG;
+    use crate::get_legal_moves::get_legal_moves_strict;
+    use rand::SeedableRng;
+    use rand::seq::IndexedRandom;
+    use rustc_hash::FxBuildHasher;
+    use std::collections::HashSet;
+
+    #[test]
+    fn bitboard_follows_board_through_random_games() {
+        let mut rng: RNG = RNG::seed_from_u64(11);
+        for size in [5, 7, 9] {
+            let mut points: Vec<u8> = vec![PointState::Empty as u8; size * size];
+            points[size + 1] = PointState::Offline as u8;
+            points[size * size - 1] = PointState::Offline as u8;
+            let mut board: Board = Board::new(points.into_boxed_slice(), size, Player::Black, 5.5, false);
+            let mut bitboard: BitBoard = BitBoard::from(&board);
+            let mut board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+            board_history.insert(board.hash);
+
+            for _ in 0..size * size * 3 {
+                assert_eq!(bitboard.legal_moves(&board_history), board.legal_moves(&board_history));
+                assert_eq!(bitboard.legal_moves_strict(&board_history), get_legal_moves_strict(&board, &board_history));
+                assert_eq!(bitboard.true_eyes(), board.true_eyes());
+                assert_eq!(bitboard.playout_moves(&board_history), board.playout_moves(&board_history));
+                assert_eq!(bitboard.score(), board.score());
+
+                let legal_moves: Vec<usize> = board.legal_moves(&board_history).collect();
+                match legal_moves.choose(&mut rng) {
+                    Some(point) => {
+                        board = board.play(*point);
+                        bitboard = bitboard.play(*point);
+                    }
+                    None => {
+                        board = board.pass();
+                        bitboard = bitboard.pass();
+                    }
+                }
+                assert_eq!(bitboard.hash, board.hash);
+                board_history.insert(board.hash);
+            }
+        }
+    }
+}
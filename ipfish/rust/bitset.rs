@@ -1,73 +1,167 @@
-use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Not};
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Not, Shl, Shr};
 
-#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+// The number of 64 bit words backing a bitset.
+const WORDS: usize = 6;
+
+/// A fixed capacity set of points. The capacity is large enough to hold every point of a 19x19 board.
+/// Stored as a handful of words rather than a single one, because a 64 bit set can't even fit a 9x9 board.
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
 pub struct BitSet {
-    bits: usize,
+    words: [u64; WORDS],
 }
 
 impl BitSet {
+    /// The largest number of points a bitset can hold. Enough for a 19x19 board.
+    pub const CAPACITY: usize = WORDS * u64::BITS as usize;
+
+    #[inline]
     pub fn insert(&mut self, n: usize) {
-        assert!(n < usize::BITS as usize);
-        self.bits |= 1 << n;
+        assert!(n < BitSet::CAPACITY);
+        self.words[n / 64] |= 1 << (n % 64);
+    }
+    #[inline]
+    pub fn remove(&mut self, n: usize) {
+        assert!(n < BitSet::CAPACITY);
+        self.words[n / 64] &= !(1 << (n % 64));
     }
+    #[inline]
     pub fn contains(&self, n: usize) -> bool {
-        assert!(n < usize::BITS as usize);
-        return self.bits & (1 << n) > 0;
+        assert!(n < BitSet::CAPACITY);
+        return self.words[n / 64] & (1 << (n % 64)) > 0;
     }
+    #[inline]
     pub fn new() -> BitSet {
-        return BitSet { bits: 0 };
+        return BitSet { words: [0; WORDS] };
     }
+    #[inline]
     pub fn len(&self) -> usize {
-        return self.bits.count_ones() as usize;
+        return self.words.iter().map(|word| word.count_ones() as usize).sum();
     }
+    #[inline]
     pub fn first(&self) -> Option<usize> {
-        if self.empty() { None } else { Some(self.bits.trailing_zeros() as usize) }
+        for (index, word) in self.words.iter().enumerate() {
+            if *word != 0 {
+                return Some(index * 64 + word.trailing_zeros() as usize);
+            }
+        }
+        return None;
     }
+    #[inline]
     pub fn empty(&self) -> bool {
-        self.bits == 0
+        self.words.iter().all(|word| *word == 0)
     }
 }
 
 impl BitOr for BitSet {
     type Output = Self;
-    fn bitor(self, rhs: Self) -> Self::Output {
-        return BitSet { bits: self.bits | rhs.bits };
+    #[inline]
+    fn bitor(mut self, rhs: Self) -> Self::Output {
+        self |= rhs;
+        return self;
     }
 }
 impl BitOrAssign for BitSet {
+    #[inline]
     fn bitor_assign(&mut self, rhs: Self) {
-        self.bits = self.bits | rhs.bits;
+        for n in 0..WORDS {
+            self.words[n] |= rhs.words[n];
+        }
     }
 }
 impl BitAnd for BitSet {
     type Output = Self;
-    fn bitand(self, rhs: Self) -> Self::Output {
-        return BitSet { bits: self.bits & rhs.bits };
+    #[inline]
+    fn bitand(mut self, rhs: Self) -> Self::Output {
+        self &= rhs;
+        return self;
     }
 }
 impl BitAndAssign for BitSet {
+    #[inline]
     fn bitand_assign(&mut self, rhs: Self) {
-        self.bits = self.bits & rhs.bits;
+        for n in 0..WORDS {
+            self.words[n] &= rhs.words[n];
+        }
     }
 }
 impl Not for BitSet {
     type Output = Self;
 
-    fn not(self) -> Self::Output {
-        return BitSet { bits: !self.bits };
+    #[inline]
+    fn not(mut self) -> Self::Output {
+        for n in 0..WORDS {
+            self.words[n] = !self.words[n];
+        }
+        return self;
+    }
+}
+
+// Shifting moves every point up by n, bits shifted past the capacity are lost.
+impl Shl<usize> for BitSet {
+    type Output = Self;
+
+    #[inline]
+    fn shl(self, n: usize) -> Self::Output {
+        // Shifts by less than a word are what neighbour calculations use, so they get a fast path.
+        if n > 0 && n < 64 {
+            let mut result: BitSet = BitSet::new();
+            result.words[0] = self.words[0] << n;
+            for index in 1..WORDS {
+                result.words[index] = (self.words[index] << n) | (self.words[index - 1] >> (64 - n));
+            }
+            return result;
+        }
+        let word_shift: usize = n / 64;
+        let bit_shift: usize = n % 64;
+        let mut result: BitSet = BitSet::new();
+        for index in word_shift..WORDS {
+            result.words[index] = self.words[index - word_shift] << bit_shift;
+            if bit_shift > 0 && index > word_shift {
+                result.words[index] |= self.words[index - word_shift - 1] >> (64 - bit_shift);
+            }
+        }
+        return result;
+    }
+}
+// Shifting moves every point down by n, bits shifted below 0 are lost.
+impl Shr<usize> for BitSet {
+    type Output = Self;
+
+    #[inline]
+    fn shr(self, n: usize) -> Self::Output {
+        if n > 0 && n < 64 {
+            let mut result: BitSet = BitSet::new();
+            for index in 0..WORDS - 1 {
+                result.words[index] = (self.words[index] >> n) | (self.words[index + 1] << (64 - n));
+            }
+            result.words[WORDS - 1] = self.words[WORDS - 1] >> n;
+            return result;
+        }
+        let word_shift: usize = n / 64;
+        let bit_shift: usize = n % 64;
+        let mut result: BitSet = BitSet::new();
+        for index in 0..WORDS - word_shift {
+            result.words[index] = self.words[index + word_shift] >> bit_shift;
+            if bit_shift > 0 && index + word_shift + 1 < WORDS {
+                result.words[index] |= self.words[index + word_shift + 1] << (64 - bit_shift);
+            }
+        }
+        return result;
     }
 }
 
 impl Iterator for BitSet {
     type Item = usize;
+    #[inline]
     fn next(&mut self) -> Option<Self::Item> {
-        if self.bits == 0 {
-            return None;
-        } else {
-            let trailing_zeros: usize = self.bits.trailing_zeros() as usize;
-            self.bits &= self.bits - 1;
-            return Some(trailing_zeros);
+        for (index, word) in self.words.iter_mut().enumerate() {
+            if *word != 0 {
+                let trailing_zeros: usize = word.trailing_zeros() as usize;
+                *word &= *word - 1;
+                return Some(index * 64 + trailing_zeros);
+            }
         }
+        return None;
     }
 }
 
@@ -105,4 +199,47 @@ mod tests {
         assert_eq!(hashset.contains(&3), false);
         assert_eq!(hashset.contains(&14), false);
     }
+
+    #[test]
+    fn nineteen_by_nineteen_bitset() {
+        let mut my_bitset: BitSet = BitSet::new();
+        assert_eq!(my_bitset.first(), None);
+
+        my_bitset.insert(360);
+        my_bitset.insert(64);
+        my_bitset.insert(63);
+        my_bitset.insert(200);
+
+        assert_eq!(my_bitset.contains(360), true);
+        assert_eq!(my_bitset.contains(64), true);
+        assert_eq!(my_bitset.contains(63), true);
+        assert_eq!(my_bitset.contains(65), false);
+        assert_eq!(my_bitset.first(), Some(63));
+        assert_eq!(my_bitset.len(), 4);
+
+        my_bitset.remove(63);
+        assert_eq!(my_bitset.first(), Some(64));
+
+        let mut other_bitset: BitSet = BitSet::new();
+        other_bitset.insert(200);
+        other_bitset.insert(300);
+        assert_eq!((my_bitset & other_bitset).collect::<Vec<usize>>(), vec![200]);
+        assert_eq!((my_bitset | other_bitset).collect::<Vec<usize>>(), vec![64, 200, 300, 360]);
+        assert_eq!((my_bitset & !other_bitset).collect::<Vec<usize>>(), vec![64, 360]);
+    }
+
+    #[test]
+    fn shifting_across_words() {
+        let mut my_bitset: BitSet = BitSet::new();
+        my_bitset.insert(0);
+        my_bitset.insert(63);
+        my_bitset.insert(300);
+
+        assert_eq!((my_bitset << 1).collect::<Vec<usize>>(), vec![1, 64, 301]);
+        assert_eq!((my_bitset << 19).collect::<Vec<usize>>(), vec![19, 82, 319]);
+        assert_eq!((my_bitset << 100).collect::<Vec<usize>>(), vec![100, 163]);
+        assert_eq!((my_bitset >> 1).collect::<Vec<usize>>(), vec![62, 299]);
+        assert_eq!((my_bitset >> 19).collect::<Vec<usize>>(), vec![44, 281]);
+        assert_eq!((my_bitset >> 250).collect::<Vec<usize>>(), vec![50]);
+    }
 }
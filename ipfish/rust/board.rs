@@ -1,8 +1,24 @@
 use rustc_hash::FxBuildHasher;
 
+use crate::bitset::BitSet;
+use crate::get_adjacent_points::get_adjacent_points;
 use crate::player::Player;
+use crate::point_state::PointState;
+use crate::zobrist::zobrist_hash;
 use std::collections::HashSet;
 
+// The chain id of points that don't have a stone on them.
+pub const NO_CHAIN: u16 = u16::MAX;
+
+/// A group of connected stones of the same colour.
+#[derive(Clone, Copy, Debug)]
+pub struct Chain {
+    // The stones that make up the chain.
+    pub stones: BitSet,
+    // The empty points adjacent to the chain.
+    pub liberties: BitSet,
+}
+
 #[derive(Clone, Debug)]
 pub struct Board {
     // The current pieces of the board.
@@ -15,6 +31,172 @@ pub struct Board {
     pub komi: f64,
     // True if the opponent passed last turn, false otherwise.
     pub opponent_passed: bool,
+    // The zobrist hash of the pieces on the board. Kept up to date by make_move and pass_move.
+    pub hash: u64,
+    // For every point, the index into chains of the chain the stone there belongs to. NO_CHAIN for points without a stone.
+    pub chain_ids: Box<[u16]>,
+    // Every chain currently on the board. Kept up to date by make_move.
+    pub chains: Vec<Chain>,
+}
+
+impl Board {
+    /// Builds a board from its raw points, computing the derived state such as the hash and chains.
+    ///
+    /// # Arguments
+    ///
+    /// * `board` - The pieces of the board.
+    /// * `size` - The size of the board (the square root of the number of points on it).
+    /// * `player` - Who's turn it currently is to play.
+    /// * `komi` - The bonus points that white gets to their score.
+    /// * `opponent_passed` - True if the opponent passed last turn, false otherwise.
+    pub fn new(board: Box<[u8]>, size: usize, player: Player, komi: f64, opponent_passed: bool) -> Board {
+        let mut result: Board = Board {
+            hash: zobrist_hash(&board),
+            chain_ids: vec![NO_CHAIN; board.len()].into_boxed_slice(),
+            chains: Vec::new(),
+            board: board,
+            size: size,
+            player: player,
+            komi: komi,
+            opponent_passed: opponent_passed,
+        };
+        result.build_chains();
+        return result;
+    }
+
+    /// Returns the chain of the stone at `point`.
+    /// Panics if there's no stone there.
+    ///
+    /// # Arguments
+    ///
+    /// * `point` - A point that has a black or white stone on it.
+    pub fn chain(&self, point: usize) -> &Chain {
+        return &self.chains[self.chain_ids[point] as usize];
+    }
+
+    /// Returns true if there's a black or white stone at `point`.
+    pub fn has_stone(&self, point: usize) -> bool {
+        return self.chain_ids[point] != NO_CHAIN;
+    }
+
+    /// Flood fills every stone on the board into chains, finding their liberties along the way.
+    fn build_chains(&mut self) {
+        for point in 0..self.board.len() {
+            let colour: u8 = self.board[point];
+            if (colour != PointState::Black as u8 && colour != PointState::White as u8) || self.has_stone(point) {
+                continue;
+            }
+
+            let chain_id: u16 = self.chains.len() as u16;
+            let mut chain: Chain = Chain {
+                stones: BitSet::new(),
+                liberties: BitSet::new(),
+            };
+            let mut to_visit: Vec<usize> = vec![point];
+            chain.stones.insert(point);
+            while let Some(stone) = to_visit.pop() {
+                self.chain_ids[stone] = chain_id;
+                for adjacent_point in get_adjacent_points(stone, self) {
+                    if self.board[adjacent_point] == PointState::Empty as u8 {
+                        chain.liberties.insert(adjacent_point);
+                    } else if self.board[adjacent_point] == colour && !chain.stones.contains(adjacent_point) {
+                        chain.stones.insert(adjacent_point);
+                        to_visit.push(adjacent_point);
+                    }
+                }
+            }
+            self.chains.push(chain);
+        }
+    }
 }
 
-pub type BoardHistory = HashSet<Box<[u8]>, FxBuildHasher>;
+// The zobrist hashes of all the positions the board has been in.
+pub type BoardHistory = HashSet<u64, FxBuildHasher>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board_from_string::board_from_string;
+    use crate::RNG;
+    use crate::get_legal_moves::get_legal_moves;
+    use crate::make_move::make_move;
+    use crate::point::Point;
+    use rand::SeedableRng;
+    use rand::seq::IndexedRandom;
+
+    /// Checks the incrementally updated chains and hash against ones built from scratch.
+    fn assert_chains_match_rebuild(board: &Board) {
+        let rebuilt: Board = Board::new(board.board.clone(), board.size, board.player, board.komi, board.opponent_passed);
+        assert_eq!(board.hash, rebuilt.hash, "Hash drifted");
+        assert_eq!(board.chains.len(), rebuilt.chains.len(), "Wrong number of chains");
+        for point in 0..board.board.len() {
+            assert_eq!(board.has_stone(point), rebuilt.has_stone(point), "Chain presence differs at {}", point);
+            if board.has_stone(point) {
+                assert_eq!(board.chain(point).stones, rebuilt.chain(point).stones, "Stones differ at {}", point);
+                assert_eq!(board.chain(point).liberties, rebuilt.chain(point).liberties, "Liberties differ at {}", point);
+            }
+        }
+    }
+
+    #[test]
+    fn chains_are_tracked_incrementally() {
+        let board: Box<[u8]> = board_from_string(
+            "
+    .OO.#
+    OOO..
+    .OOOX
+    OOOO.
+    #O#.X
+    ",
+            5,
+        );
+        let board: Board = Board::new(board, 5, Player::Black, 7.5, false);
+        assert_eq!(board.chains.len(), 3);
+        assert_eq!(board.chain(11).liberties.len(), 6);
+        assert_eq!(board.chain(11).stones.len(), 13);
+
+        // Black connects its two stones.
+        let board: Board = make_move(Point::from_index(9, 25), &board);
+        assert_chains_match_rebuild(&board);
+        assert_eq!(board.chains.len(), 2);
+        assert_eq!(board.chain(4).stones.len(), 3);
+
+        let board: Board = make_move(Point::from_index(20, 25), &board);
+        assert_chains_match_rebuild(&board);
+
+        // White captures the lone black stone in the corner.
+        let board: Board = make_move(Point::from_index(23, 25), &board);
+        assert_chains_match_rebuild(&board);
+        let board: Board = make_move(Point::from_index(18, 25), &board);
+        assert_chains_match_rebuild(&board);
+        assert_eq!(board.has_stone(23), false);
+
+        // Black self ataris, then white captures the whole group.
+        let board: Board = make_move(Point::from_index(3, 25), &board);
+        assert_chains_match_rebuild(&board);
+        assert_eq!(board.chain(3).stones.len(), 4);
+        assert_eq!(board.chain(3).liberties.len(), 1);
+        let board: Board = make_move(Point::from_index(19, 25), &board);
+        assert_chains_match_rebuild(&board);
+        assert_eq!(board.chains.len(), 1);
+        assert_eq!(board.chain(11).liberties.contains(3), true);
+    }
+
+    #[test]
+    fn chains_survive_random_games() {
+        let mut rng: RNG = RNG::seed_from_u64(7);
+        for _ in 0..20 {
+            let mut board: Board = Board::new(vec![PointState::Empty as u8; 49].into_boxed_slice(), 7, Player::Black, 5.5, false);
+            let mut board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+            for _ in 0..150 {
+                let legal_moves: Vec<usize> = get_legal_moves(&board, &board_history).collect();
+                match legal_moves.choose(&mut rng) {
+                    Some(point) => board = make_move(Point::from_index(*point, 49), &board),
+                    None => break,
+                }
+                board_history.insert(board.hash);
+                assert_chains_match_rebuild(&board);
+            }
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use std::fmt;
+
+use crate::board::Board;
+use crate::player::Player;
+use crate::point::{Move, Point};
+use crate::point_state::PointState;
+
+/// Prints the board the way parse_board reads it, top row first, with the column letters above and below
+/// and the row numbers on both sides.
+/// . is an empty point, X a black stone, O a white stone and # an offline point.
+/// The alternate form, {:#}, starts with parse_board's header line, so the whole board can be parsed back.
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            let player: &str = if self.player == Player::Black { "black" } else { "white" };
+            let passed: &str = if self.opponent_passed { " passed" } else { "" };
+            writeln!(f, "{} komi {}{}", player, self.komi, passed)?;
+        }
+        let cells: Vec<String> = (0..self.board.len()).map(|point| point_char(self.board[point]).to_string()).collect();
+        return write_grid(f, self.size, &cells);
+    }
+}
+
+/// Values for every point of a board, like an analysis or an ownership map, printed on top of the board.
+/// The stones and offline points are printed as they are on the board, the empty points get their value.
+/// Values that aren't finite are printed as - for -infinity, like the moves an analysis doesn't look at, and ? for NaN.
+/// If there's one more value than there are points, it's the value of passing, printed below the board.
+pub struct Heatmap<'a> {
+    board: &'a Board,
+    values: &'a [f64],
+    // Whether the stones get their value too, rather than being printed as they are.
+    values_on_stones: bool,
+}
+
+impl<'a> Heatmap<'a> {
+    /// The heatmap of `values` on the board. Panics if there isn't a value for every point, or one more for passing.
+    pub fn new(board: &'a Board, values: &'a [f64]) -> Heatmap<'a> {
+        let point_count: usize = board.board.len();
+        assert!(
+            values.len() == point_count || values.len() == point_count + 1,
+            "{} values don't fit a board with {} points",
+            values.len(),
+            point_count
+        );
+        return Heatmap {
+            board: board,
+            values: values,
+            values_on_stones: false,
+        };
+    }
+
+    /// Prints the values of the stones too, for values that mean something there, like the ownership.
+    pub fn with_values_on_stones(mut self) -> Heatmap<'a> {
+        self.values_on_stones = true;
+        return self;
+    }
+}
+
+impl fmt::Display for Heatmap<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let point_count: usize = self.board.board.len();
+        let cells: Vec<String> = (0..point_count)
+            .map(|point| match self.board.board[point] {
+                x if x == PointState::Empty as u8 => format_value(self.values[point]),
+                x if self.values_on_stones && x != PointState::Offline as u8 => format_value(self.values[point]),
+                x => point_char(x).to_string(),
+            })
+            .collect();
+        write_grid(f, self.board.size, &cells)?;
+        if self.values.len() > point_count {
+            writeln!(f, "{}: {}", Move::Pass.label(self.board.size), format_value(self.values[point_count]))?;
+        }
+        return Ok(());
+    }
+}
+
+fn point_char(point_state: u8) -> char {
+    return match point_state {
+        x if x == PointState::Empty as u8 => '.',
+        x if x == PointState::Black as u8 => 'X',
+        x if x == PointState::White as u8 => 'O',
+        x if x == PointState::Offline as u8 => '#',
+        _ => '?',
+    };
+}
+
+fn format_value(value: f64) -> String {
+    if value == f64::NEG_INFINITY {
+        return String::from("-");
+    }
+    if value.is_nan() {
+        return String::from("?");
+    }
+    return format!("{:.2}", value);
+}
+
+/// Writes a cell for every point, right aligned in columns as wide as the widest cell, with the coordinates around them.
+fn write_grid(f: &mut fmt::Formatter<'_>, size: usize, cells: &[String]) -> fmt::Result {
+    let cell_width: usize = cells.iter().map(|cell| cell.chars().count()).max().unwrap_or(1);
+    let label_width: usize = size.to_string().len();
+
+    let mut column_labels: String = " ".repeat(label_width);
+    for x in 0..size {
+        let label: String = Point::from_xy(x, 0, size).label(size);
+        column_labels.push_str(&format!(" {:>cell_width$}", &label[..1]));
+    }
+    writeln!(f, "{}", column_labels)?;
+    for y in (0..size).rev() {
+        write!(f, "{:>label_width$}", y + 1)?;
+        for x in 0..size {
+            write!(f, " {:>cell_width$}", cells[Point::from_xy(x, y, size).index()])?;
+        }
+        writeln!(f, " {}", y + 1)?;
+    }
+    return writeln!(f, "{}", column_labels);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board_from_string::board_from_string;
+
+    #[test]
+    fn boards_print_like_they_are_read() {
+        let board: Board = Board::new(
+            board_from_string(
+                "
+    .X.
+    O.#
+    ...
+    ",
+                3,
+            ),
+            3,
+            Player::Black,
+            5.5,
+            false,
+        );
+        assert_eq!(board.to_string(), "  a b c\n3 . X . 3\n2 O . # 2\n1 . . . 1\n  a b c\n");
+        assert!(format!("{:#}", board).starts_with("black komi 5.5\n  a b c\n"));
+
+        let mut analysis: Vec<f64> = vec![0.25; 10];
+        analysis[0] = f64::NEG_INFINITY;
+        analysis[4] = f64::NAN;
+        analysis[9] = -0.5;
+        let heatmap: Heatmap = Heatmap::new(&board, &analysis);
+        assert_eq!(
+            heatmap.to_string(),
+            "     a    b    c\n3 0.25    X 0.25 3\n2    O    ?    # 2\n1    - 0.25 0.25 1\n     a    b    c\npass: -0.50\n"
+        );
+
+        // The ownership means something for the stones too, but not for offline points.
+        let ownership: Vec<f64> = vec![1.0, 1.0, 1.0, -1.0, 0.0, 0.0, 0.5, 1.0, 0.5];
+        assert_eq!(
+            Heatmap::new(&board, &ownership).with_values_on_stones().to_string(),
+            "      a     b     c\n3  0.50  1.00  0.50 3\n2 -1.00  0.00     # 2\n1  1.00  1.00  1.00 1\n      a     b     c\n"
+        );
+    }
+}
@@ -1,10 +1,16 @@
+use std::fmt;
 use std::vec;
 
+use crate::bitset::BitSet;
+use crate::board::Board;
+use crate::player::Player;
+use crate::point::Point;
 use crate::point_state::PointState;
 
 /// Builds an internal board representation from a string.
-/// For the string, . is an empty space, X is a black stone, O is a white stone and # is an offline stone. Other character such as whitespace, are ignored.
-/// Panics if the number of returned points isn't equal to the square of the board size.
+/// For the string, . is an empty space, X is a black stone, O is a white stone and # is an offline stone.
+/// The string is read by parse_board, so whitespace and coordinate labels are fine, anything else panics.
+/// Panics if the board isn't `board_size` points across.
 ///
 /// # Arguments
 ///
@@ -12,34 +18,212 @@ use crate::point_state::PointState;
 /// * `board_size` - The size of the board, which is the square root of the number of points on the board.
 #[allow(dead_code)] // Only used in tests
 pub fn board_from_string(board_str: &str, board_size: usize) -> Box<[u8]> {
-    let number_of_points: usize = board_size * board_size;
-    let mut result: Vec<u8> = vec![0; number_of_points];
+    let board: Board = match parse_board(board_str) {
+        Ok(board) => board,
+        Err(error) => panic!("{}", error),
+    };
+    assert_eq!(board.size, board_size, "The board is {} points across, not {}", board.size, board_size);
+    return board.board;
+}
+
+/// Why a board couldn't be parsed, and where. Lines and columns count from 1.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BoardParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for BoardParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{} at line {}, column {}", self.message, self.line, self.column);
+    }
+}
 
-    let mut n: usize = 0;
+impl std::error::Error for BoardParseError {}
+
+/// Parses a whole board, written the way Board's Display prints it.
+/// Every row is a line of . for empty points, X for black stones, O for white stones and # for offline points, top row first.
+/// Whitespace between the points is ignored, and so are the row numbers at either end of a row and lines of column letters.
+/// The size is the number of rows, and every row has to be as long.
+///
+/// An optional header line before the rows sets up the rest of the board, with any of these words in any order:
+/// black or white for the player to move, komi followed by the komi, and passed if the opponent passed last turn.
+/// Without it, it's black to move with no komi, like `black komi 0`.
+///
+/// # Arguments
+///
+/// * `text` - The board, as text.
+pub fn parse_board(text: &str) -> Result<Board, BoardParseError> {
+    let mut player: Player = Player::Black;
+    let mut komi: f64 = 0.0;
+    let mut opponent_passed: bool = false;
+    // Each row with the line it's on.
+    let mut rows: Vec<(usize, Vec<u8>)> = Vec::new();
+    let mut last_line: usize = 0;
 
-    for letter in String::from(board_str).chars() {
-        if n >= number_of_points {
-            break;
+    for (index, line) in text.lines().enumerate() {
+        let line_number: usize = index + 1;
+        let words: Vec<(usize, &str)> = words(line);
+        if words.is_empty() {
+            continue;
+        }
+        last_line = line_number;
+        if rows.is_empty() && ["black", "white", "komi", "passed"].contains(&words[0].1) {
+            let mut words = words.into_iter();
+            while let Some((column, word)) = words.next() {
+                let error = |message: String| BoardParseError {
+                    line: line_number,
+                    column: column,
+                    message: message,
+                };
+                match word {
+                    "black" => player = Player::Black,
+                    "white" => player = Player::White,
+                    "passed" => opponent_passed = true,
+                    "komi" => {
+                        komi = match words.next() {
+                            Some((column, value)) => value.parse::<f64>().map_err(|_| BoardParseError {
+                                line: line_number,
+                                column: column,
+                                message: format!("{} isn't a komi", value),
+                            })?,
+                            None => return Err(error(String::from("The komi is missing"))),
+                        }
+                    }
+                    _ => return Err(error(format!("Unknown header word {}", word))),
+                }
+            }
+            continue;
+        }
+        // Column letters, like the ones Display prints.
+        if words.iter().all(|(_, word)| word.len() == 1 && word.as_bytes()[0].is_ascii_lowercase()) {
+            continue;
         }
 
-        let column: usize = n % board_size;
-        let row: usize = (number_of_points - n - 1) / board_size;
-        let location: usize = row * board_size + column;
-
-        if letter == '.' {
-            result[location] = PointState::Empty as u8;
-            n += 1;
-        } else if letter == 'X' {
-            result[location] = PointState::Black as u8;
-            n += 1;
-        } else if letter == 'O' {
-            result[location] = PointState::White as u8;
-            n += 1;
-        } else if letter == '#' {
-            result[location] = PointState::Offline as u8;
-            n += 1;
+        let mut row: Vec<u8> = Vec::new();
+        for (index, letter) in line.char_indices() {
+            let column: usize = line[..index].chars().count() + 1;
+            // Row numbers are only allowed before the first point and after the last one.
+            let is_label: bool = letter.is_ascii_digit() && (row.is_empty() || line[index..].trim_end().bytes().all(|c| c.is_ascii_digit()));
+            let point_state: PointState = match letter {
+                '.' => PointState::Empty,
+                'X' => PointState::Black,
+                'O' => PointState::White,
+                '#' => PointState::Offline,
+                _ if letter.is_whitespace() || is_label => continue,
+                _ => {
+                    return Err(BoardParseError {
+                        line: line_number,
+                        column: column,
+                        message: format!("Unexpected character {}", letter),
+                    });
+                }
+            };
+            row.push(point_state as u8);
         }
+        rows.push((line_number, row));
     }
 
-    return result.into_boxed_slice();
+    if rows.is_empty() {
+        return Err(BoardParseError {
+            line: last_line.max(1),
+            column: 1,
+            message: String::from("There are no rows"),
+        });
+    }
+    let size: usize = rows[0].1.len();
+    for (line, row) in &rows {
+        if row.len() != size {
+            return Err(BoardParseError {
+                line: *line,
+                column: 1,
+                message: format!("The row has {} points, the first row has {}", row.len(), size),
+            });
+        }
+    }
+    if rows.len() != size || size * size > BitSet::CAPACITY {
+        return Err(BoardParseError {
+            line: rows[rows.len() - 1].0,
+            column: 1,
+            message: format!("{} rows of {} points isn't a supported board", rows.len(), size),
+        });
+    }
+
+    let mut points: Vec<u8> = vec![PointState::Empty as u8; size * size];
+    for (index, (_, row)) in rows.iter().enumerate() {
+        let y: usize = size - 1 - index;
+        for x in 0..size {
+            points[Point::from_xy(x, y, size).index()] = row[x];
+        }
+    }
+    return Ok(Board::new(points.into_boxed_slice(), size, player, komi, opponent_passed));
+}
+
+/// The words of a line split by whitespace and commas, with the columns they start at.
+fn words(line: &str) -> Vec<(usize, &str)> {
+    let mut result: Vec<(usize, &str)> = Vec::new();
+    let mut start: Option<usize> = None;
+    for (index, letter) in line.char_indices().chain([(line.len(), ' ')]) {
+        let separator: bool = letter.is_whitespace() || letter == ',';
+        match start {
+            Some(word_start) if separator => {
+                result.push((line[..word_start].chars().count() + 1, &line[word_start..index]));
+                start = None;
+            }
+            None if !separator => start = Some(index),
+            _ => {}
+        }
+    }
+    return result;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boards_are_parsed_with_their_header() {
+        let board: Board = parse_board(
+            "
+    white, komi 7.5, passed
+    .X#
+    O..
+    ..X
+    ",
+        )
+        .unwrap();
+        assert_eq!(board.size, 3);
+        assert_eq!(board.player, Player::White);
+        assert_eq!(board.komi, 7.5);
+        assert!(board.opponent_passed);
+        // The first row is the top one.
+        assert_eq!(board.board[Point::from_label("c3", 3).unwrap().index()], PointState::Offline as u8);
+        assert_eq!(board.board[Point::from_label("c1", 3).unwrap().index()], PointState::Black as u8);
+
+        // What Display prints parses back to the same board.
+        let printed: Board = parse_board(&format!("{:#}", board)).unwrap();
+        assert_eq!(printed.board, board.board);
+        assert_eq!((printed.player, printed.komi, printed.opponent_passed), (Player::White, 7.5, true));
+        let plain: Board = parse_board(&board.to_string()).unwrap();
+        assert_eq!((plain.player, plain.komi, plain.opponent_passed), (Player::Black, 0.0, false));
+    }
+
+    #[test]
+    fn bad_boards_say_where_they_went_wrong() {
+        let error = |text: &str| parse_board(text).unwrap_err();
+        assert_eq!(
+            error(".X.\n.x.\n..."),
+            BoardParseError {
+                line: 2,
+                column: 2,
+                message: String::from("Unexpected character x"),
+            }
+        );
+        assert_eq!(error("...\n..\n...").message, "The row has 2 points, the first row has 3");
+        assert_eq!(error("...\n...").message, "2 rows of 3 points isn't a supported board");
+        assert_eq!(error("komi\n.").column, 1);
+        assert_eq!(error("black komi five\n.").to_string(), "five isn't a komi at line 1, column 12");
+        assert_eq!(error("\n\n").message, "There are no rows");
+    }
 }
@@ -0,0 +1,166 @@
+use rustc_hash::FxBuildHasher;
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::bitset::BitSet;
+use crate::board::{Board, BoardHistory};
+use crate::player::Player;
+use crate::point_state::PointState;
+use crate::zobrist::zobrist_hash;
+
+/// What's wrong with a board javascript passed in. Boards are numbered by their index into the board history.
+#[derive(Clone, Debug, PartialEq)]
+pub enum InputError {
+    /// There's no current board, the board history is empty.
+    EmptyHistory,
+    /// The current board's number of points isn't the square of a supported board size.
+    NonSquareBoard { points: usize },
+    /// A point isn't one of the PointState values.
+    BadPointValue { board: usize, point: usize, value: u8 },
+    /// A board in the history has a different number of points than the current board.
+    InconsistentSizes { board: usize, points: usize, expected: usize },
+    /// The turn isn't black's or white's PointState value.
+    BadTurn { turn: f64 },
+    /// The chain with a stone at `point` on the current board has no liberties, it should have been captured.
+    ChainWithoutLiberties { point: usize },
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            InputError::EmptyHistory => write!(f, "The board history is empty, there's no current board"),
+            InputError::NonSquareBoard { points } => write!(f, "The current board has {} points, which isn't a supported square board", points),
+            InputError::BadPointValue { board, point, value } => {
+                write!(f, "Board {} of the history has the invalid value {} at point {}", board, value, point)
+            }
+            InputError::InconsistentSizes { board, points, expected } => {
+                write!(
+                    f,
+                    "Board {} of the history has {} points, but the current board has {}",
+                    board, points, expected
+                )
+            }
+            InputError::BadTurn { turn } => write!(
+                f,
+                "The turn is {}, it should be {} for black or {} for white",
+                turn,
+                Player::Black as u8,
+                Player::White as u8
+            ),
+            InputError::ChainWithoutLiberties { point } => {
+                write!(
+                    f,
+                    "The chain at point {} of the current board has no liberties, it should have been captured",
+                    point
+                )
+            }
+        };
+    }
+}
+
+impl std::error::Error for InputError {}
+
+/// Checks the boards javascript passed in, and reads the current board and the board history from them.
+/// See get_analysis for what the arguments mean.
+///
+/// # Arguments
+///
+/// * `input_history` - The points of every board the game has been in, the last one is the current board.
+/// * `komi` - The extra points white gets for the final score.
+/// * `turn` - Whether it's black or white's turn to play, as a PointState value.
+/// * `opponent_passed` - Whether the opponent passed last turn.
+pub fn board_from_input(input_history: Vec<Vec<u8>>, komi: f64, turn: f64, opponent_passed: bool) -> Result<(Board, BoardHistory), InputError> {
+    let Some(current_board) = input_history.last() else {
+        return Err(InputError::EmptyHistory);
+    };
+    let points: usize = current_board.len();
+    let size: usize = points.isqrt();
+    if points == 0 || size * size != points || points > BitSet::CAPACITY {
+        return Err(InputError::NonSquareBoard { points: points });
+    }
+    if turn != Player::Black as u8 as f64 && turn != Player::White as u8 as f64 {
+        return Err(InputError::BadTurn { turn: turn });
+    }
+    let player: Player = Player::from(turn);
+
+    let mut board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+    for (index, board) in input_history.iter().enumerate() {
+        if board.len() != points {
+            return Err(InputError::InconsistentSizes {
+                board: index,
+                points: board.len(),
+                expected: points,
+            });
+        }
+        if let Some(point) = board
+            .iter()
+            .position(|value| !(PointState::Empty as u8..=PointState::Offline as u8).contains(value))
+        {
+            return Err(InputError::BadPointValue {
+                board: index,
+                point: point,
+                value: board[point],
+            });
+        }
+        board_history.insert(zobrist_hash(board));
+    }
+
+    let board: Board = Board::new(current_board.clone().into_boxed_slice(), size, player, komi, opponent_passed);
+    if let Some(point) = chain_without_liberties(&board) {
+        return Err(InputError::ChainWithoutLiberties { point: point });
+    }
+    return Ok((board, board_history));
+}
+
+/// The lowest point with a stone whose chain has no liberties, None if every chain has some.
+/// No game can get to a board like that, the chain would have been captured.
+pub fn chain_without_liberties(board: &Board) -> Option<usize> {
+    return (0..board.board.len()).find(|point| board.has_stone(*point) && board.chain(*point).liberties.empty());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bad_input_is_rejected() {
+        let empty: Vec<u8> = vec![PointState::Empty as u8; 25];
+        let read = |history: Vec<Vec<u8>>, turn: f64| board_from_input(history, 5.5, turn, false).map(|(board, _)| board.size);
+
+        assert_eq!(read(vec![empty.clone(), empty.clone()], 2.0), Ok(5));
+        assert_eq!(read(Vec::new(), 2.0), Err(InputError::EmptyHistory));
+        assert_eq!(
+            read(vec![vec![PointState::Empty as u8; 24]], 2.0),
+            Err(InputError::NonSquareBoard { points: 24 })
+        );
+        assert_eq!(read(vec![Vec::new()], 2.0), Err(InputError::NonSquareBoard { points: 0 }));
+        assert_eq!(read(vec![empty.clone()], 4.0), Err(InputError::BadTurn { turn: 4.0 }));
+        assert_eq!(read(vec![empty.clone()], 2.5), Err(InputError::BadTurn { turn: 2.5 }));
+
+        let mut bad_point: Vec<u8> = empty.clone();
+        bad_point[7] = 0;
+        assert_eq!(
+            read(vec![bad_point, empty.clone()], 3.0),
+            Err(InputError::BadPointValue { board: 0, point: 7, value: 0 })
+        );
+        assert_eq!(
+            read(vec![vec![PointState::Empty as u8; 49], empty.clone()], 3.0),
+            Err(InputError::InconsistentSizes {
+                board: 0,
+                points: 49,
+                expected: 25
+            })
+        );
+
+        // The white stones took black's last liberty, but the black stone is still there.
+        let mut captured: Vec<u8> = empty.clone();
+        captured[0] = PointState::Black as u8;
+        captured[1] = PointState::White as u8;
+        captured[5] = PointState::White as u8;
+        assert_eq!(read(vec![empty.clone(), captured], 2.0), Err(InputError::ChainWithoutLiberties { point: 0 }));
+        assert_eq!(
+            InputError::BadTurn { turn: 4.0 }.to_string(),
+            "The turn is 4, it should be 2 for black or 3 for white"
+        );
+    }
+}
@@ -0,0 +1,59 @@
+/// Returns the current wall clock time in milliseconds.
+/// In the browser std::time isn't available, so we ask javascript instead.
+#[cfg(target_arch = "wasm32")]
+pub fn now_ms() -> f64 {
+    return js_sys::Date::now();
+}
+
+/// Returns the current wall clock time in milliseconds.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now_ms() -> f64 {
+    let since_epoch: std::time::Duration = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+    return since_epoch.as_secs_f64() * 1000.0;
+}
+
+/// The point in time a search has to be finished by.
+#[derive(Clone, Copy, Debug)]
+pub struct Deadline {
+    end_ms: f64,
+}
+
+impl Deadline {
+    /// A deadline `budget_ms` milliseconds from now.
+    pub fn after(budget_ms: f64) -> Deadline {
+        return Deadline { end_ms: now_ms() + budget_ms };
+    }
+
+    /// A deadline that never passes. Searches given this have to be bounded some other way.
+    pub fn never() -> Deadline {
+        return Deadline { end_ms: f64::INFINITY };
+    }
+
+    /// True once the deadline has passed.
+    pub fn expired(&self) -> bool {
+        return now_ms() >= self.end_ms;
+    }
+
+    /// The milliseconds left until the deadline, zero if it has passed.
+    pub fn remaining_ms(&self) -> f64 {
+        return (self.end_ms - now_ms()).max(0.0);
+    }
+
+    /// A deadline that uses up `fraction` of the time remaining until this one.
+    pub fn fraction(&self, fraction: f64) -> Deadline {
+        return Deadline::after(self.remaining_ms() * fraction);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deadlines_expire() {
+        assert_eq!(Deadline::after(0.0).expired(), true);
+        assert_eq!(Deadline::after(60000.0).expired(), false);
+        assert_eq!(Deadline::never().expired(), false);
+        assert!(Deadline::after(60000.0).fraction(0.5).remaining_ms() <= 30000.0);
+    }
+}
@@ -0,0 +1,455 @@
+use rand::SeedableRng;
+use std::fmt;
+use wasm_bindgen::prelude::*;
+
+use crate::RNG;
+use crate::board::{Board, BoardHistory};
+use crate::clock::Deadline;
+use crate::engine_config::EngineConfig;
+use crate::evaluator::{Evaluator, RandomPlayouts};
+use crate::get_legal_moves::get_legal_moves_strict;
+use crate::make_move::play_move;
+use crate::mcts_strategy::{MCTree, initialize_tree, mcts_analysis, mcts_step};
+use crate::pick_strategy::pick_strategy;
+use crate::player::Player;
+use crate::point::{Move, Point};
+use crate::sgf::{GameRecord, read_sgf, write_sgf};
+use crate::strategy::Analysis;
+use crate::{board_from_js, set_panic_hook};
+
+/// Why the engine can't play a move javascript told it about. The game carries on as if it hadn't been asked.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MoveError {
+    /// The point isn't on the board.
+    OffBoard { point: usize, points: usize },
+    /// It's the other player's turn, `player` is whose move it was said to be.
+    OutOfTurn { player: Player, played: String },
+    /// The point is taken, or playing there is suicide or repeats an earlier board.
+    Illegal { played: String },
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            MoveError::OffBoard { point, points } => write!(f, "{} is not a point on a board with {} points", point, points),
+            MoveError::OutOfTurn { player, played } => write!(f, "It's not {:?}'s turn to play {}", player, played),
+            MoveError::Illegal { played } => write!(f, "{} is not a legal move", played),
+        };
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+/// A game in progress, kept alive between analyses so the search doesn't start from scratch every move.
+/// Javascript creates one per game, tells it about every move that's made, and asks it for an analysis when it's our turn.
+#[wasm_bindgen]
+pub struct Engine {
+    board: Board,
+    board_history: BoardHistory,
+    // The game so far, from the board the engine was started on.
+    record: GameRecord,
+    // The player we're analyzing for.
+    our_player: Player,
+    // The search tree rooted at the current board, if there is one.
+    tree: Option<MCTree>,
+    // True if the search was stopped, steps do nothing until the next move is played.
+    stopped: bool,
+    // What the getters report on: the latest analysis, or the search so far if the search has grown since.
+    // Worked out the first time a getter asks, so they all share it. None if it hasn't been worked out since the search last grew.
+    current: Option<Analysis>,
+    config: EngineConfig,
+    // Judges the leaf nodes of the search tree.
+    evaluator: Box<dyn Evaluator>,
+    rng: RNG,
+}
+
+#[wasm_bindgen]
+impl Engine {
+    /// Starts following a game. The arguments are the same as for get_analysis, we play as whoever's turn it is.
+    /// Throws an error saying what's wrong if they don't make up a valid board.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        input_history: &js_sys::Array,
+        komi: &js_sys::Number,
+        turn: &js_sys::Number,
+        opponent_passed: &js_sys::Boolean,
+        config: &EngineConfig,
+    ) -> Result<Engine, JsError> {
+        set_panic_hook();
+        let (board, board_history): (Board, BoardHistory) = board_from_js(input_history, komi, turn, opponent_passed)?;
+        return Ok(Engine::from_board(board, board_history, *config, js_sys::Math::random().to_bits()));
+    }
+
+    /// Starts following a game read from SGF, picking up after its last move. We play as whoever's turn it is.
+    /// Throws an error saying what's wrong with the SGF if it can't be read.
+    pub fn from_sgf(sgf: &str, config: &EngineConfig) -> Result<Engine, JsError> {
+        set_panic_hook();
+        let record: GameRecord = read_sgf(sgf)?;
+        let (board, board_history): (Board, BoardHistory) = record.replay();
+        let mut engine: Engine = Engine::from_board(board, board_history, *config, js_sys::Math::random().to_bits());
+        engine.record = record;
+        return Ok(engine);
+    }
+
+    /// The game so far as SGF, from the board the engine was started on, for archiving it or replaying it in a viewer.
+    pub fn sgf(&self) -> String {
+        return write_sgf(&self.record);
+    }
+
+    /// Performs an analysis on the current board, the same as get_analysis's scores. Higher number = better move.
+    ///
+    /// # Arguments
+    ///
+    /// * `budget_ms` - How long to think for, in milliseconds.
+    pub fn analysis(&mut self, budget_ms: f64) -> js_sys::Float64Array {
+        let deadline: Deadline = Deadline::after(budget_ms);
+        let result: Analysis = self.analyze(deadline);
+        let scores: js_sys::Float64Array = js_sys::Float64Array::from(result.scores.as_slice());
+        self.current = Some(result);
+        return scores;
+    }
+
+    /// The ownership map that goes with the latest analysis, or with the search so far if it has grown since.
+    /// For every point, from -1 if it's sure to end up white's, through 0 for undecided or neutral, to 1 if it's sure to end up black's.
+    /// Empty if no games have been played out.
+    pub fn ownership(&mut self) -> js_sys::Float64Array {
+        return js_sys::Float64Array::from(self.ownership_map().as_slice());
+    }
+
+    /// The score lead of every move, that goes with the latest analysis like the ownership. Indexed like the analysis.
+    /// How many points the player to move is expected to win by after the move and the opponent's best reply, NaN for moves that weren't looked at.
+    /// Empty if the strategy didn't estimate the score.
+    pub fn score_leads(&mut self) -> js_sys::Float64Array {
+        let leads: &[f64] = self.current().score_leads.as_deref().unwrap_or_default();
+        return js_sys::Float64Array::from(leads);
+    }
+
+    /// Searches the current board a little more, with Monte Carlo Tree Search.
+    /// Returns false without searching if the search has been stopped.
+    ///
+    /// # Arguments
+    ///
+    /// * `iterations` - The number of MCTS iterations to add to the search tree.
+    ///   Every iteration evaluates a new leaf with the config's mcts_simulations playouts.
+    pub fn step(&mut self, iterations: u32) -> bool {
+        if self.stopped {
+            return false;
+        }
+        let tree: &mut MCTree = self.tree.get_or_insert_with(|| {
+            initialize_tree(
+                self.board.clone(),
+                &self.board_history,
+                self.evaluator.as_ref(),
+                self.config.rave_schedule(),
+                &mut self.rng,
+            )
+        });
+        mcts_step(tree, &self.board_history, self.evaluator.as_ref(), iterations, &mut self.rng);
+        self.current = None;
+        return true;
+    }
+
+    /// The analysis of the search so far, in the same format as get_analysis's scores, or the latest analysis if the search hasn't grown since.
+    /// Moves that haven't been explored yet are scored -infinity.
+    pub fn current_analysis(&mut self) -> js_sys::Float64Array {
+        return js_sys::Float64Array::from(self.current().scores.as_slice());
+    }
+
+    /// Stops the search, further steps do nothing until the next move is played.
+    /// Call this when the game state changes, before telling the engine about the move.
+    pub fn stop(&mut self) {
+        self.stopped = true;
+    }
+
+    /// Plays our move on the point, or passes if there's no point.
+    /// Throws an error saying what's wrong if it isn't our turn or the move isn't legal, and doesn't play it.
+    pub fn play_our_move(&mut self, point: Option<usize>) -> Result<(), JsError> {
+        self.try_play(self.our_player, point)?;
+        return Ok(());
+    }
+
+    /// Plays the opponent's move on the point, or passes if there's no point.
+    /// Throws an error saying what's wrong if it isn't their turn or the move isn't legal, and doesn't play it.
+    pub fn play_opponent_move(&mut self, point: Option<usize>) -> Result<(), JsError> {
+        self.try_play(!self.our_player, point)?;
+        return Ok(());
+    }
+}
+
+impl Engine {
+    /// Starts following a game from a board we already have. We play as whoever's turn it is.
+    ///
+    /// # Arguments
+    ///
+    /// * `board` - The current board.
+    /// * `board_history` - All the states the board has been in, including the current one.
+    /// * `config` - How to analyze.
+    /// * `seed` - The seed for the RNG used in MC playouts.
+    pub fn from_board(board: Board, board_history: BoardHistory, config: EngineConfig, seed: u64) -> Engine {
+        return Engine {
+            our_player: board.player,
+            record: GameRecord::new(board.clone()),
+            board: board,
+            board_history: board_history,
+            tree: None,
+            stopped: false,
+            current: None,
+            config: config,
+            evaluator: Box::new(RandomPlayouts {
+                simulation_count: config.mcts_simulations,
+            }),
+            rng: RNG::seed_from_u64(seed),
+        };
+    }
+
+    /// Analyzes the current board until the deadline, growing the search tree kept from earlier moves.
+    pub fn analyze(&mut self, deadline: Deadline) -> Analysis {
+        let tree: &mut MCTree = self.tree.get_or_insert_with(|| {
+            initialize_tree(
+                self.board.clone(),
+                &self.board_history,
+                self.evaluator.as_ref(),
+                self.config.rave_schedule(),
+                &mut self.rng,
+            )
+        });
+        return pick_strategy(
+            &self.board,
+            &self.board_history,
+            &self.config,
+            self.evaluator.as_ref(),
+            Some(tree),
+            deadline,
+            &mut self.rng,
+        );
+    }
+
+    /// The analysis of the search so far, see current_analysis.
+    pub fn search_analysis(&self) -> Analysis {
+        return match &self.tree {
+            Some(tree) => mcts_analysis(tree, &self.board_history),
+            None => Analysis::new(&self.board),
+        };
+    }
+
+    /// The latest analysis, or the analysis of the search so far if the search has grown since.
+    /// It's only worked out once until the search grows again, however many getters ask for it.
+    fn current(&mut self) -> &Analysis {
+        if self.current.is_none() {
+            self.current = Some(self.search_analysis());
+        }
+        return self.current.as_ref().unwrap();
+    }
+
+    /// The map behind ownership.
+    pub fn ownership_map(&mut self) -> Vec<f64> {
+        return match &self.current().ownership {
+            Some(ownership) => ownership.map(),
+            None => Vec::new(),
+        };
+    }
+
+    /// Checks a move javascript told us about and plays it. Nothing changes if it can't be played.
+    ///
+    /// # Arguments
+    ///
+    /// * `player` - Whose move it's meant to be.
+    /// * `point` - The index of the point played on, None for passing.
+    pub fn try_play(&mut self, player: Player, point: Option<usize>) -> Result<(), MoveError> {
+        let points: usize = self.board.board.len();
+        let played: Move = match point {
+            Some(point) if point >= points => return Err(MoveError::OffBoard { point: point, points: points }),
+            Some(point) => Move::Play(Point::from_index(point, points)),
+            None => Move::Pass,
+        };
+        if player != self.board.player {
+            return Err(MoveError::OutOfTurn {
+                player: player,
+                played: played.label(self.board.size),
+            });
+        }
+        if let Move::Play(point) = played
+            && !get_legal_moves_strict(&self.board, &self.board_history).contains(point.index())
+        {
+            return Err(MoveError::Illegal {
+                played: played.label(self.board.size),
+            });
+        }
+        self.play(played);
+        return Ok(());
+    }
+
+    /// Makes a legal move for whoever's turn it is. The part of the search tree below the move is kept, the rest is thrown away.
+    fn play(&mut self, played: Move) {
+        self.board = play_move(played, &self.board);
+        self.board_history.insert(self.board.hash);
+        self.record.moves.push(played);
+        self.tree = self.tree.take().and_then(|tree| tree.reroot(played));
+        self.current = None;
+        // There's a new position to search.
+        self.stopped = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board_from_string::board_from_string;
+    use crate::engine_config::RaveChoice;
+    use crate::mcts_strategy::{Edge, RaveSchedule, mcts_search};
+    use crate::point_state::PointState;
+    use rustc_hash::FxBuildHasher;
+    use std::collections::HashSet;
+
+    #[test]
+    fn the_tree_follows_the_game() {
+        let board: Board = Board::new(vec![PointState::Empty as u8; 25].into_boxed_slice(), 5, Player::Black, 5.5, false);
+        let mut board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+        board_history.insert(board.hash);
+        let mut engine: Engine = Engine::from_board(board, board_history, EngineConfig::default(), 9);
+
+        // Grow a tree the way the analysis would.
+        let mut tree: MCTree = initialize_tree(
+            engine.board.clone(),
+            &engine.board_history,
+            engine.evaluator.as_ref(),
+            engine.config.rave_schedule(),
+            &mut engine.rng,
+        );
+        while !tree
+            .child(0, Move::from_index(12, 25))
+            .is_some_and(|node| node.edges.iter().any(|edge| edge.child.is_some()))
+        {
+            mcts_search(
+                &mut tree,
+                &engine.board_history,
+                engine.evaluator.as_ref(),
+                Deadline::after(5.0),
+                &mut engine.rng,
+            );
+        }
+        let reply_edge: &Edge = tree
+            .child(0, Move::from_index(12, 25))
+            .unwrap()
+            .edges
+            .iter()
+            .find(|edge| edge.child.is_some())
+            .unwrap();
+        let reply: Move = reply_edge.played;
+        let reply_visits: f64 = reply_edge.blackwins + reply_edge.whitewins;
+        engine.tree = Some(tree);
+
+        engine.try_play(Player::Black, Some(12)).unwrap();
+        engine.try_play(Player::White, reply.point().map(Point::index)).unwrap();
+
+        // What was searched below the moves is still there.
+        let tree: &MCTree = engine.tree.as_ref().expect("The tree survives the moves");
+        assert_eq!(tree.nodes[0].board.hash, engine.board.hash);
+        assert_eq!(tree.nodes[0].board.player, Player::Black);
+        assert!(tree.nodes[0].blackwins + tree.nodes[0].whitewins >= reply_visits);
+        assert_eq!(engine.board_history.len(), 3);
+        assert_eq!(engine.record.moves, vec![Move::from_index(12, 25), reply]);
+        assert_eq!(read_sgf(&engine.sgf()).unwrap().replay().0.hash, engine.board.hash);
+        for (_, index) in tree.transpositions.iter() {
+            assert!(*index < tree.nodes.len());
+        }
+
+        // Playing a move that was never searched throws the tree away.
+        if let Some(unexplored) = tree.nodes[0].edges.iter().find(|edge| edge.child.is_none()).map(|edge| edge.played) {
+            engine.try_play(Player::Black, unexplored.point().map(Point::index)).unwrap();
+            assert!(engine.tree.is_none());
+        }
+    }
+
+    #[test]
+    fn bad_moves_are_refused() {
+        let board: Board = Board::new(
+            board_from_string(
+                "
+    .X.
+    X..
+    ...
+    ",
+                3,
+            ),
+            3,
+            Player::Black,
+            5.5,
+            false,
+        );
+        let mut board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+        board_history.insert(board.hash);
+        let mut engine: Engine = Engine::from_board(board, board_history, EngineConfig::default(), 5);
+
+        assert_eq!(
+            engine.try_play(Player::White, Some(0)),
+            Err(MoveError::OutOfTurn {
+                player: Player::White,
+                played: String::from("a1")
+            })
+        );
+        assert_eq!(engine.try_play(Player::Black, Some(9)), Err(MoveError::OffBoard { point: 9, points: 9 }));
+        assert_eq!(engine.try_play(Player::Black, Some(7)), Err(MoveError::Illegal { played: String::from("b3") }));
+        assert!(engine.record.moves.is_empty());
+
+        // White playing in black's corner would be suicide.
+        engine.try_play(Player::Black, Some(0)).unwrap();
+        let hash: u64 = engine.board.hash;
+        assert_eq!(engine.try_play(Player::White, Some(6)), Err(MoveError::Illegal { played: String::from("a3") }));
+        assert_eq!(engine.board.hash, hash);
+        assert_eq!(engine.record.moves, vec![Move::from_index(0, 9)]);
+        assert_eq!(read_sgf(&engine.sgf()).unwrap().replay().0.hash, hash);
+
+        // Passing is always allowed, in turn.
+        engine.try_play(Player::White, None).unwrap();
+        assert_eq!(engine.board.player, Player::Black);
+    }
+
+    #[test]
+    fn searching_in_steps() {
+        let board: Board = Board::new(vec![PointState::Empty as u8; 9].into_boxed_slice(), 3, Player::Black, 5.5, false);
+        let mut board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+        board_history.insert(board.hash);
+        let mut engine: Engine = Engine::from_board(board, board_history, EngineConfig::default(), 10);
+        assert!(engine.search_analysis().scores.iter().all(|score| *score == f64::NEG_INFINITY));
+
+        // Every move and passing gets explored, before anything is explored twice.
+        assert!(engine.step(10));
+        let scores: Vec<f64> = engine.search_analysis().scores;
+        assert_eq!(scores.len(), 10);
+        assert!(scores.iter().all(|score| score.is_finite()));
+        assert_eq!(
+            engine.tree.as_ref().unwrap().nodes[0].blackwins + engine.tree.as_ref().unwrap().nodes[0].whitewins,
+            250.0
+        );
+
+        // Without an analysis, the ownership and the score leads are the search's.
+        assert_eq!(engine.ownership_map().len(), 9);
+        assert!(engine.current().score_leads.as_ref().unwrap().iter().all(|lead| lead.is_finite()));
+        // The getters share the analysis until the search grows again.
+        assert_eq!(engine.current.as_ref().map(|analysis| analysis.scores.clone()), Some(scores.clone()));
+
+        engine.stop();
+        assert!(!engine.step(10));
+        assert_eq!(engine.search_analysis().scores, scores);
+
+        // A move is a new position to search.
+        engine.try_play(Player::Black, Some(4)).unwrap();
+        assert!(engine.step(1));
+    }
+
+    #[test]
+    fn the_config_picks_the_rave_schedule() {
+        assert_eq!(EngineConfig::default().rave_schedule(), RaveSchedule::default());
+
+        let board: Board = Board::new(vec![PointState::Empty as u8; 9].into_boxed_slice(), 3, Player::Black, 5.5, false);
+        let mut board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+        board_history.insert(board.hash);
+        let mut config: EngineConfig = EngineConfig::default();
+        config.rave = RaveChoice::MinimumError;
+        config.rave_bias = 0.2;
+        let mut engine: Engine = Engine::from_board(board, board_history, config, 11);
+        assert!(engine.step(1));
+        assert_eq!(engine.tree.as_ref().unwrap().rave_schedule, RaveSchedule::MinimumError(0.2));
+    }
+}
@@ -0,0 +1,98 @@
+use wasm_bindgen::prelude::*;
+
+use crate::mcts_strategy::RaveSchedule;
+
+/// Which strategy to analyze with.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StrategyChoice {
+    /// Guesstimate who's winning, and pick a strategy based on that. See pick_strategy.
+    Auto,
+    /// Always Monte Carlo Tree Search.
+    MonteCarloTreeSearch,
+    /// Always minimax, scored with the final score.
+    Minimax,
+}
+
+/// How Monte Carlo Tree Search blends in the all moves as first statistics, see RaveSchedule.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RaveChoice {
+    /// Plain UCT, the all moves as first statistics are ignored.
+    Off,
+    /// RaveSchedule::Equivalence, with rave_equivalence as k.
+    Equivalence,
+    /// RaveSchedule::MinimumError, with rave_bias as b.
+    MinimumError,
+}
+
+/// Tunes how the engine analyzes, without having to recompile it.
+/// Javascript creates one with the defaults and sets the fields it wants to change.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct EngineConfig {
+    /// Which strategy to analyze with.
+    pub strategy: StrategyChoice,
+    /// Play tengen as the first move of the game, if the board is big enough and tengen is online.
+    pub tengen_opening: bool,
+    /// The share of the time budget the guesstimate of who's winning can take.
+    pub guesstimate_share: f64,
+    /// The guesstimate plays at least this many simulations, even if that takes longer than its share of the budget.
+    pub guesstimate_min_simulations: u32,
+    /// The guesstimate plays at most this many simulations.
+    pub guesstimate_max_simulations: u32,
+    /// Above this share of guesstimate wins we're winning, and minimax finishes the game.
+    pub minimax_threshold: f64,
+    /// Below this share of guesstimate wins we've lost, and just pass.
+    pub pass_threshold: f64,
+    /// The number of MC simulations done on every leaf node of the Monte Carlo Search Tree.
+    pub mcts_simulations: u32,
+    /// How the Monte Carlo Search Tree blends in the all moves as first statistics.
+    pub rave: RaveChoice,
+    /// For the Equivalence schedule, the number of visits after which both statistics get equal weight.
+    pub rave_equivalence: f64,
+    /// For the MinimumError schedule, the bias of the all moves as first statistics.
+    pub rave_bias: f64,
+    /// Once moves win more often than minimax_threshold, pick between them by how much they win by rather than how often.
+    /// Off by default, winning is all that counts.
+    pub maximize_score: bool,
+}
+
+#[wasm_bindgen]
+impl EngineConfig {
+    /// The default configuration.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> EngineConfig {
+        return EngineConfig::default();
+    }
+}
+
+impl EngineConfig {
+    /// The RaveSchedule the rave fields make up.
+    pub fn rave_schedule(&self) -> RaveSchedule {
+        return match self.rave {
+            RaveChoice::Off => RaveSchedule::Off,
+            RaveChoice::Equivalence => RaveSchedule::Equivalence(self.rave_equivalence),
+            RaveChoice::MinimumError => RaveSchedule::MinimumError(self.rave_bias),
+        };
+    }
+}
+
+impl Default for EngineConfig {
+    fn default() -> EngineConfig {
+        return EngineConfig {
+            strategy: StrategyChoice::Auto,
+            tengen_opening: true,
+            guesstimate_share: 0.05,
+            guesstimate_min_simulations: 10,
+            guesstimate_max_simulations: 100,
+            minimax_threshold: 0.95,
+            pass_threshold: 0.05,
+            mcts_simulations: 25,
+            rave: RaveChoice::Equivalence,
+            rave_equivalence: 1000.0,
+            rave_bias: 0.1,
+            maximize_score: false,
+        };
+    }
+}
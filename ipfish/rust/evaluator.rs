@@ -0,0 +1,302 @@
+use std::collections::VecDeque;
+
+use crate::RNG;
+use crate::benson::{SettledPoints, settled_points};
+use crate::bitboard::BitBoard;
+use crate::board::{Board, BoardHistory};
+use crate::final_score::final_score;
+use crate::get_adjacent_points::get_adjacent_points;
+use crate::montecarlo_score::{PlayedPoints, Winner, montecarlo_playout};
+use crate::ownership::Ownership;
+use crate::player::Player;
+use crate::playout_board::PlayoutBoard;
+use crate::point_state::PointState;
+use crate::score_stats::ScoreStats;
+
+// How many points of estimated score make the territory heuristic about 73% sure of the result.
+const TERRITORY_SCALE: f64 = 2.0;
+
+// The share of the prior probability the territory heuristic gives to passing.
+const TERRITORY_PASS_PRIOR: f64 = 0.05;
+
+/// Judges board positions, for the search strategies to use on the positions they don't search any deeper.
+pub trait Evaluator {
+    /// Evaluates the board, from black's point of view.
+    ///
+    /// # Arguments
+    ///
+    /// * `board` - The board to evaluate.
+    /// * `board_history` - The board history used for superko, including the board itself.
+    /// * `rng` - RNG for evaluators that need it.
+    fn evaluate(&self, board: &Board, board_history: &BoardHistory, rng: &mut RNG) -> Evaluation;
+
+    /// How many games an evaluation is worth, when it's added up with other evaluations in a search tree.
+    fn weight(&self) -> f64 {
+        return 1.0;
+    }
+}
+
+/// What an evaluator thinks of a board.
+pub struct Evaluation {
+    /// The expected result for black, from 0 for a sure loss to 1 for a sure win.
+    pub value: f64,
+    /// The final score margins black is expected to win by, weighted the same as the value.
+    pub score: ScoreStats,
+    /// How promising every move is, indexed by point with passing last. None if the evaluator has no idea.
+    pub priors: Option<Vec<f64>>,
+    /// The all moves as first statistics of the games played out during the evaluation, if any were.
+    pub amaf: Option<Amaf>,
+    /// Who owned every point at the end of the games played out during the evaluation, if any were.
+    pub ownership: Option<Ownership>,
+}
+
+/// All moves as first statistics of a batch of simulations.
+/// For both players and every point, the black and white wins of the simulations in which that player played on the point.
+pub struct Amaf {
+    black_moves: Vec<(f64, f64)>,
+    white_moves: Vec<(f64, f64)>,
+}
+
+impl Amaf {
+    pub fn new(point_count: usize) -> Amaf {
+        return Amaf {
+            black_moves: vec![(0.0, 0.0); point_count],
+            white_moves: vec![(0.0, 0.0); point_count],
+        };
+    }
+
+    /// Adds a simulation to the statistics.
+    pub fn record(&mut self, played: &PlayedPoints, winner: Winner) {
+        for (points, moves) in [(played.black, &mut self.black_moves), (played.white, &mut self.white_moves)] {
+            for point in points {
+                match winner {
+                    Winner::BlackWin => moves[point].0 += 1.0,
+                    Winner::WhiteWin => moves[point].1 += 1.0,
+                }
+            }
+        }
+    }
+
+    /// The black and white wins of the simulations where the player played the point.
+    pub fn wins(&self, player: Player, point: usize) -> (f64, f64) {
+        return match player {
+            Player::Black => self.black_moves[point],
+            Player::White => self.white_moves[point],
+        };
+    }
+}
+
+/// Plays out random games from the board, the value is the share of them black won.
+/// No priors, but it does keep the all moves as first statistics and the ownership of the games.
+pub struct RandomPlayouts {
+    pub simulation_count: u32,
+}
+
+impl Default for RandomPlayouts {
+    fn default() -> RandomPlayouts {
+        return RandomPlayouts { simulation_count: 25 };
+    }
+}
+
+impl Evaluator for RandomPlayouts {
+    fn evaluate(&self, board: &Board, board_history: &BoardHistory, rng: &mut RNG) -> Evaluation {
+        let bitboard: BitBoard = BitBoard::from(board);
+        let mut amaf: Amaf = Amaf::new(board.board.len());
+        let mut ownership: Ownership = Ownership::new(board.board.len());
+        let mut score: ScoreStats = ScoreStats::new();
+        let mut black_wins: f64 = 0.0;
+        let settled: SettledPoints = settled_points(board);
+        for _ in 0..self.simulation_count {
+            let mut played: PlayedPoints = PlayedPoints::new();
+            let final_board: BitBoard = montecarlo_playout(bitboard.clone(), board_history.clone(), &settled, &mut played, rng);
+            let final_score: f64 = final_board.score();
+            let winner: Winner = Winner::from_score(final_score);
+            amaf.record(&played, winner);
+            ownership.record(&final_board);
+            score.add(final_score, 1.0);
+            black_wins += winner as u32 as f64;
+        }
+        return Evaluation {
+            value: black_wins / self.simulation_count as f64,
+            score: score,
+            priors: None,
+            amaf: Some(amaf),
+            ownership: Some(ownership),
+        };
+    }
+
+    fn weight(&self) -> f64 {
+        return self.simulation_count as f64;
+    }
+}
+
+/// Scores the board as if the game ended now, with final_score. No priors.
+/// The value goes up and down with the score, so maximizing one maximizes the other.
+pub struct FinalScore;
+
+impl Evaluator for FinalScore {
+    fn evaluate(&self, board: &Board, _board_history: &BoardHistory, _rng: &mut RNG) -> Evaluation {
+        // Neither player can win by more than the number of points on the board, plus komi.
+        let max_margin: f64 = board.board.len() as f64 + board.komi.abs() + 1.0;
+        let score: f64 = final_score(board);
+        return Evaluation {
+            value: 0.5 + 0.5 * score / max_margin,
+            score: ScoreStats::single(score, 1.0),
+            priors: None,
+            amaf: None,
+            ownership: None,
+        };
+    }
+}
+
+/// Estimates the score by giving every empty point to the player with the closest stone, and turns that into a win probability.
+/// The empty points about as close to both players are the contested ones, and they get most of the prior probability.
+pub struct TerritoryHeuristic;
+
+impl Evaluator for TerritoryHeuristic {
+    fn evaluate(&self, board: &Board, _board_history: &BoardHistory, _rng: &mut RNG) -> Evaluation {
+        let black_distances: Vec<usize> = distances_to(board, Player::Black);
+        let white_distances: Vec<usize> = distances_to(board, Player::White);
+
+        let mut score: f64 = -board.komi;
+        let mut priors: Vec<f64> = vec![0.0; board.board.len() + 1];
+        for point in 0..board.board.len() {
+            if board.board[point] == PointState::Black as u8 {
+                score += 1.0;
+            } else if board.board[point] == PointState::White as u8 {
+                score -= 1.0;
+            } else if board.board[point] == PointState::Empty as u8 {
+                if black_distances[point] < white_distances[point] {
+                    score += 1.0;
+                } else if white_distances[point] < black_distances[point] {
+                    score -= 1.0;
+                }
+                priors[point] = 1.0 / (1.0 + black_distances[point].abs_diff(white_distances[point]) as f64);
+            }
+        }
+
+        let total: f64 = priors.iter().sum();
+        if total > 0.0 {
+            for prior in priors.iter_mut() {
+                *prior *= (1.0 - TERRITORY_PASS_PRIOR) / total;
+            }
+            priors[board.board.len()] = TERRITORY_PASS_PRIOR;
+        } else {
+            priors[board.board.len()] = 1.0;
+        }
+
+        return Evaluation {
+            value: 1.0 / (1.0 + (-score / TERRITORY_SCALE).exp()),
+            score: ScoreStats::single(score, 1.0),
+            priors: Some(priors),
+            amaf: None,
+            ownership: None,
+        };
+    }
+}
+
+/// The number of steps through empty points from every point to the closest stone of the player.
+/// Points that can't be reached count as being as far away as the board is big.
+fn distances_to(board: &Board, player: Player) -> Vec<usize> {
+    let mut distances: Vec<usize> = vec![board.board.len(); board.board.len()];
+    let mut queue: VecDeque<usize> = VecDeque::new();
+    for point in 0..board.board.len() {
+        if board.board[point] == player as u8 {
+            distances[point] = 0;
+            queue.push_back(point);
+        }
+    }
+    while let Some(point) = queue.pop_front() {
+        for adjacent_point in get_adjacent_points(point, board) {
+            if board.board[adjacent_point] == PointState::Empty as u8 && distances[adjacent_point] > distances[point] + 1 {
+                distances[adjacent_point] = distances[point] + 1;
+                queue.push_back(adjacent_point);
+            }
+        }
+    }
+    return distances;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board_from_string::board_from_string;
+    use rand::SeedableRng;
+    use rustc_hash::FxBuildHasher;
+    use std::collections::HashSet;
+
+    #[test]
+    fn evaluators_agree_on_a_settled_board() {
+        let board: Board = Board::new(
+            board_from_string(
+                "
+    .X.X.
+    XXXXX
+    XXXXX
+    OOOOO
+    .O.O.
+    ",
+                5,
+            ),
+            5,
+            Player::Black,
+            0.5,
+            false,
+        );
+        let board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+        let mut rng: RNG = RNG::seed_from_u64(12);
+
+        let playouts: Evaluation = RandomPlayouts::default().evaluate(&board, &board_history, &mut rng);
+        assert_eq!(playouts.value, 1.0);
+        assert!(playouts.priors.is_none());
+        assert!(playouts.amaf.is_some());
+        assert_eq!(playouts.ownership.expect("Playouts keep the ownership").share(Player::Black, 24), 1.0);
+
+        // Nothing can change on the board, so black wins every game by the same margin.
+        assert_eq!(playouts.score.mean, 4.5);
+        assert_eq!(playouts.score.variance(), 0.0);
+
+        let score: Evaluation = FinalScore.evaluate(&board, &board_history, &mut rng);
+        assert!(score.value > 0.5 && score.value < 1.0);
+        assert_eq!(score.score.mean, 4.5);
+
+        let territory: Evaluation = TerritoryHeuristic.evaluate(&board, &board_history, &mut rng);
+        assert!(territory.value > 0.5);
+        let priors: Vec<f64> = territory.priors.expect("The territory heuristic has priors");
+        assert!((priors.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert_eq!(priors[6], 0.0, "Occupied points aren't moves");
+    }
+
+    #[test]
+    fn territory_goes_to_the_closest_stones() {
+        let board: Board = Board::new(
+            board_from_string(
+                "
+    .....
+    .X...
+    .....
+    ...O.
+    .....
+    ",
+                5,
+            ),
+            5,
+            Player::Black,
+            0.0,
+            false,
+        );
+        let black_distances: Vec<usize> = distances_to(&board, Player::Black);
+        assert_eq!(black_distances[16], 0);
+        assert_eq!(black_distances[0], 4);
+        assert_eq!(black_distances[4], 6);
+        assert_eq!(black_distances[20], 2);
+
+        let board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+        let evaluation: Evaluation = TerritoryHeuristic.evaluate(&board, &board_history, &mut RNG::seed_from_u64(13));
+        // The position is symmetric, so it's even, and the points in between are the most contested.
+        assert_eq!(evaluation.value, 0.5);
+        let priors: Vec<f64> = evaluation.priors.unwrap();
+        assert!(priors[12] > priors[4]);
+        assert_eq!(priors[4], priors[20]);
+    }
+}
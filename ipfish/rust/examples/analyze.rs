@@ -0,0 +1,48 @@
+use rand::prelude::*;
+use rustc_hash::FxBuildHasher;
+use std::collections::HashSet;
+use std::io::Read;
+
+use ipfish::board::{Board, BoardHistory};
+use ipfish::board_display::Heatmap;
+use ipfish::board_from_string::parse_board;
+use ipfish::clock::Deadline;
+use ipfish::engine::Engine;
+use ipfish::engine_config::EngineConfig;
+use ipfish::strategy::Analysis;
+
+/// Analyzes a board read from stdin, in the format parse_board reads, and prints the analysis on top of the board.
+/// The first argument is how long to think for in milliseconds, a second by default.
+/// For example: echo "white komi 5.5
+/// .....
+/// .X...
+/// ..O..
+/// .....
+/// ....." | cargo run --release --example analyze 2000
+fn main() {
+    let budget_ms: f64 = std::env::args()
+        .nth(1)
+        .map(|budget| budget.parse().expect("The budget should be a number"))
+        .unwrap_or(1000.0);
+    let mut input: String = String::new();
+    std::io::stdin().read_to_string(&mut input).expect("Couldn't read the board");
+    let board: Board = match parse_board(&input) {
+        Ok(board) => board,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    };
+
+    let mut board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+    board_history.insert(board.hash);
+    let mut engine: Engine = Engine::from_board(board.clone(), board_history, EngineConfig::default(), rand::rng().random());
+    let analysis: Analysis = engine.analyze(Deadline::after(budget_ms));
+
+    println!("{:#}", board);
+    println!("The analysis is\n{}", Heatmap::new(&board, &analysis.scores));
+    if let Some(ownership) = &analysis.ownership {
+        println!("The ownership is\n{}", Heatmap::new(&board, &ownership.map()).with_values_on_stones());
+    }
+    println!("The best move is {}", analysis.best_move().label(board.size));
+}
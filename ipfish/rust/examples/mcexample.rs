@@ -1,4 +1,4 @@
-#![allow(warnings)]
+#![allow(warnings, clippy::approx_constant)]
 use rand::prelude::*;
 use rustc_hash::FxBuildHasher;
 use std::cell::Cell;
@@ -6,27 +6,24 @@ use std::collections::HashSet;
 
 use ipfish::RNG;
 use ipfish::board::{Board, BoardHistory};
-use ipfish::montecarlo_score::montecarlo_score;
+use ipfish::board_display::Heatmap;
+use ipfish::montecarlo_score::{MontecarloResult, montecarlo_score};
 use ipfish::player::Player;
+use ipfish::zobrist::zobrist_hash;
 
 fn main() {
     let mut rng = RNG::from_rng(&mut rand::rng());
 
     let empty_fivebyfive_board: Box<[u8]> = vec![1; 25].into_boxed_slice();
     let mut board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher::default());
-    board_history.insert(empty_fivebyfive_board.clone());
+    board_history.insert(zobrist_hash(&empty_fivebyfive_board));
 
-    let board: Board = Board {
-        board: empty_fivebyfive_board,
-        komi: 5.5,
-        size: 5,
-        player: Player::Black,
-        opponent_passed: false,
-    };
+    let board: Board = Board::new(empty_fivebyfive_board, 5, Player::Black, 5.5, false);
 
-    let chance_of_winning = montecarlo_score(&board, &board_history, 3000, &mut rng);
+    let result: MontecarloResult = montecarlo_score(&board, &board_history, 3000, &mut rng);
 
-    println!("The chance of winning is {}", chance_of_winning);
+    println!("Black won {} of 3000 games", result.black_wins);
+    println!("The ownership is\n{}", Heatmap::new(&board, &result.ownership.map()).with_values_on_stones());
 }
 
 use hashbrown::HashMap;
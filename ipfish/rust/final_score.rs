@@ -1,3 +1,4 @@
+use crate::benson::{Benson, benson};
 use crate::bitset::BitSet;
 use crate::board::Board;
 use crate::get_adjacent_points::get_adjacent_points;
@@ -15,6 +16,53 @@ pub fn final_score(board: &Board) -> f64 {
     return score_from_stones(board) + score_from_territory(board) - board.komi;
 }
 
+/// The final score, along with how much of the board is settled for good going by Benson's algorithm.
+/// Useful to tell a score that's final from one that could still swing, and to spot dead stones final_score counts as alive.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScoreDiagnostics {
+    /// The same as final_score.
+    pub score: f64,
+    /// The unconditionally alive black stones, plus the points in the regions they keep safe.
+    pub black_settled: usize,
+    /// The unconditionally alive white stones, plus the points in the regions they keep safe.
+    pub white_settled: usize,
+    /// The online points that aren't settled for either player.
+    pub unsettled: usize,
+    /// Black stones in white's safe regions. They can't live, but final_score counts them until they're captured.
+    pub dead_black_stones: usize,
+    /// White stones in black's safe regions.
+    pub dead_white_stones: usize,
+}
+
+/// Scores the board like final_score, and works out how much of that score is settled.
+///
+/// # Arguments
+///
+/// * `board` - The board state to score.
+pub fn final_score_diagnostics(board: &Board) -> ScoreDiagnostics {
+    let mut settled: [BitSet; 2] = [BitSet::new(), BitSet::new()];
+    let mut dead_stones: [usize; 2] = [0, 0];
+    for (index, player) in [Player::Black, Player::White].into_iter().enumerate() {
+        let benson: Benson = benson(board, player);
+        for chain in benson.alive_chains {
+            settled[index] |= chain;
+        }
+        for region in benson.safe_regions {
+            settled[index] |= region;
+            dead_stones[1 - index] += region.into_iter().filter(|point| board.board[*point] == !player as u8).count();
+        }
+    }
+    let online: usize = board.board.iter().filter(|state| **state != PointState::Offline as u8).count();
+    return ScoreDiagnostics {
+        score: final_score(board),
+        black_settled: settled[0].len(),
+        white_settled: settled[1].len(),
+        unsettled: online - settled[0].len() - settled[1].len(),
+        dead_black_stones: dead_stones[0],
+        dead_white_stones: dead_stones[1],
+    };
+}
+
 fn score_from_stones(board: &Board) -> f64 {
     let mut stone_score: f64 = 0.0;
     for point in 0..board.board.len() {
@@ -79,7 +127,9 @@ pub fn score_group_territory(point: usize, board: &Board, group: &mut BitSet, se
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::bitboard::BitBoard;
     use crate::board_from_string::board_from_string;
+    use crate::playout_board::PlayoutBoard;
 
     #[test]
     fn correct_final_score() {
@@ -91,17 +141,13 @@ mod tests {
     O.X.X
     ";
         let board: Box<[u8]> = board_from_string(board_string, 5);
-        let board: Board = Board {
-            board: board,
-            size: 5,
-            player: Player::Black,
-            komi: 7.5, // This is versus the illuminati, komi is critical for this test!
-            opponent_passed: false,
-        };
+        // This is versus the illuminati, komi is critical for this test!
+        let board: Board = Board::new(board, 5, Player::Black, 7.5, false);
 
         // With the game concluded black is winning by 2.5 points, using ipvgo's scoring system.
         let result: f64 = final_score(&board);
-        assert_eq!(result, 2.5);
+        assert_eq!(BitBoard::from(&board).score(), result);
+        assert_eq!(result, 2.5, "{}", board);
     }
     #[test]
     fn glitch_with_offline_eyes() {
@@ -111,20 +157,17 @@ mod tests {
     XX.X#
     XXXX.
     .X..X
+    #####
     ",
             5,
         );
-        let board: Board = Board {
-            board: board,
-            size: 5,
-            player: Player::Black,
-            komi: 5.5, // This is versus the illuminati, komi is critical for this test!
-            opponent_passed: false,
-        };
+        // This is versus the illuminati, komi is critical for this test!
+        let board: Board = Board::new(board, 5, Player::Black, 5.5, false);
 
         // With the game concluded black is winning by 2.5 points, using ipvgo's scoring system.
         let result: f64 = final_score(&board);
-        assert_eq!(result, 11.5);
+        assert_eq!(BitBoard::from(&board).score(), result);
+        assert_eq!(result, 11.5, "{}", board);
     }
 
     #[test]
@@ -139,16 +182,41 @@ mod tests {
     ",
             5,
         );
-        let board: Board = Board {
-            board: board,
-            size: 5,
-            player: Player::Black,
-            komi: 5.5, // This is versus the illuminati, komi is critical for this test!
-            opponent_passed: false,
-        };
+        // This is versus the illuminati, komi is critical for this test!
+        let board: Board = Board::new(board, 5, Player::Black, 5.5, false);
 
         // With the game concluded black is winning by 2.5 points, using ipvgo's scoring system.
         let result: f64 = final_score(&board);
-        assert_eq!(result, -0.5);
+        assert_eq!(BitBoard::from(&board).score(), result);
+        assert_eq!(result, -0.5, "{}", board);
+    }
+
+    #[test]
+    fn diagnostics_find_the_dead_stones() {
+        let board: Box<[u8]> = board_from_string(
+            "
+    .XO..
+    XXXXX
+    OOOOO
+    O.O.O
+    OOOOO
+    ",
+            5,
+        );
+        let board: Board = Board::new(board, 5, Player::Black, 0.5, false);
+
+        // The white stone in black's eye still counts for white, until black captures it.
+        let diagnostics: ScoreDiagnostics = final_score_diagnostics(&board);
+        assert_eq!(
+            diagnostics,
+            ScoreDiagnostics {
+                score: -9.5,
+                black_settled: 10,
+                white_settled: 15,
+                unsettled: 0,
+                dead_black_stones: 0,
+                dead_white_stones: 1,
+            }
+        );
     }
 }
@@ -1,8 +1,8 @@
 use crate::bitset::BitSet;
 use crate::get_adjacent_points::get_adjacent_points;
 use crate::is_in_atari::is_in_atari;
-use crate::make_move::make_move;
 use crate::point_state::PointState;
+use crate::zobrist::hash_after_move;
 
 use crate::board::{Board, BoardHistory};
 /// Returns a bitset where the 1 bits are legal moves. This version checks for superko even for non captures.
@@ -52,8 +52,10 @@ pub fn get_legal_moves(board: &Board, board_history: &BoardHistory) -> BitSet {
         if board.board[point] != PointState::Empty as u8 {
         }
         // Captures are always legal, even if they're otherwise self captures. Unless they violate superko.
-        else if captures_enemy_group(point, board) && !violates_superko(point, board, board_history) {
-            result.insert(point);
+        else if captures_enemy_group(point, board) {
+            if !violates_superko(point, board, board_history) {
+                result.insert(point);
+            }
         }
         // Self captures that are not enemy captures are illegal.
         else if is_self_capture(point, board) {
@@ -74,7 +76,7 @@ fn is_self_capture(point: usize, board: &Board) -> bool {
         }
         // If there's an adjacent friendly group that is not in atari, then it's not self capture.
         else if board.board[adjacent_point] == board.player as u8 {
-            if !is_in_atari(adjacent_point, board, point, &mut BitSet::new()) {
+            if !is_in_atari(adjacent_point, board, point) {
                 return false;
             }
         }
@@ -88,7 +90,7 @@ pub fn captures_enemy_group(point: usize, board: &Board) -> bool {
     for adjacent_point in get_adjacent_points(point, board) {
         // If there's an adjacent enemy group that is in atari, then it captures an enemy group.
         if board.board[adjacent_point] == !board.player as u8 {
-            if is_in_atari(adjacent_point, board, point, &mut BitSet::new()) {
+            if is_in_atari(adjacent_point, board, point) {
                 return true;
             }
         }
@@ -97,13 +99,7 @@ pub fn captures_enemy_group(point: usize, board: &Board) -> bool {
 }
 
 fn violates_superko(point: usize, board: &Board, board_history: &BoardHistory) -> bool {
-    let new_position: Box<[u8]> = make_move(point, board).board;
-
-    if board_history.contains(&new_position) {
-        return true;
-    } else {
-        return false;
-    }
+    return board_history.contains(&hash_after_move(point, board));
 }
 
 #[cfg(test)]
@@ -112,8 +108,13 @@ mod tests {
     use std::collections::HashSet;
 
     use super::*;
+    use crate::bitboard::BitBoard;
     use crate::board_from_string::board_from_string;
+    use crate::make_move::make_move;
     use crate::player::Player;
+    use crate::point::Point;
+    use crate::playout_board::PlayoutBoard;
+    use crate::zobrist::zobrist_hash;
 
     #[test]
     fn basic_capture() {
@@ -137,21 +138,16 @@ mod tests {
             true, true, true, true, true, true,
         ];
 
-        let board: Board = Board {
-            board: board,
-            size: 5,
-            player: Player::Black,
-            komi: 7.5,
-            opponent_passed: false,
-        };
+        let board: Board = Board::new(board, 5, Player::Black, 7.5, false);
 
         let board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher::default());
         let legal_moves: BitSet = get_legal_moves(&board, &board_history);
+        assert_eq!(BitBoard::from(&board).legal_moves(&board_history), legal_moves, "The bitboard backend disagrees");
         for n in 0..25 as usize {
             if legal_moves.contains(n) {
-                assert_eq!(legality[n], true, "Move {} marked as legal when it should be illegal", n);
+                assert_eq!(legality[n], true, "Move {} marked as legal when it should be illegal\n{}", n, board);
             } else {
-                assert_eq!(false, legality[n], "Move {} marked as illegal when it should be legal", n);
+                assert_eq!(false, legality[n], "Move {} marked as illegal when it should be legal\n{}", n, board);
             }
         }
     }
@@ -178,20 +174,15 @@ mod tests {
             false, false, false, // Pieces there
             true,
         ];
-        let board: Board = Board {
-            board: board,
-            size: 5,
-            player: Player::Black,
-            komi: 7.5,
-            opponent_passed: false,
-        };
+        let board: Board = Board::new(board, 5, Player::Black, 7.5, false);
         let board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher::default());
         let legal_moves: BitSet = get_legal_moves(&board, &board_history);
+        assert_eq!(BitBoard::from(&board).legal_moves(&board_history), legal_moves, "The bitboard backend disagrees");
         for n in 0..25 as usize {
             if legal_moves.contains(n) {
-                assert_eq!(legality[n], true, "Move {} marked as legal when it should be illegal", n);
+                assert_eq!(legality[n], true, "Move {} marked as legal when it should be illegal\n{}", n, board);
             } else {
-                assert_eq!(false, legality[n], "Move {} marked as illegal when it should be legal", n);
+                assert_eq!(false, legality[n], "Move {} marked as illegal when it should be legal\n{}", n, board);
             }
         }
     }
@@ -213,20 +204,15 @@ mod tests {
             true, true, true,
         ];
 
-        let board: Board = Board {
-            board: board,
-            size: 5,
-            player: Player::Black,
-            komi: 7.5,
-            opponent_passed: false,
-        };
+        let board: Board = Board::new(board, 5, Player::Black, 7.5, false);
         let board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher::default());
         let legal_moves: BitSet = get_legal_moves(&board, &board_history);
+        assert_eq!(BitBoard::from(&board).legal_moves(&board_history), legal_moves, "The bitboard backend disagrees");
         for n in 0..25 as usize {
             if legal_moves.contains(n) {
-                assert_eq!(legality[n], true, "Move {} marked as legal when it should be illegal", n);
+                assert_eq!(legality[n], true, "Move {} marked as legal when it should be illegal\n{}", n, board);
             } else {
-                assert_eq!(false, legality[n], "Move {} marked as illegal when it should be legal", n);
+                assert_eq!(false, legality[n], "Move {} marked as illegal when it should be legal\n{}", n, board);
             }
         }
     }
@@ -258,23 +244,18 @@ mod tests {
             true, true, true, false, false,
         ];
 
-        let board: Board = Board {
-            board: current_board.clone(),
-            size: 5,
-            player: Player::Black,
-            komi: 7.5,
-            opponent_passed: false,
-        };
+        let board: Board = Board::new(current_board.clone(), 5, Player::Black, 7.5, false);
         let mut board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher::default());
-        board_history.insert(previous_board);
-        board_history.insert(current_board);
+        board_history.insert(zobrist_hash(&previous_board));
+        board_history.insert(zobrist_hash(&current_board));
 
         let legal_moves: BitSet = get_legal_moves(&board, &board_history);
+        assert_eq!(BitBoard::from(&board).legal_moves(&board_history), legal_moves, "The bitboard backend disagrees");
         for n in 0..25 as usize {
             if legal_moves.contains(n) {
-                assert_eq!(legality[n], true, "Move {} marked as legal when it should be illegal", n);
+                assert_eq!(legality[n], true, "Move {} marked as legal when it should be illegal\n{}", n, board);
             } else {
-                assert_eq!(false, legality[n], "Move {} marked as illegal when it should be legal", n);
+                assert_eq!(false, legality[n], "Move {} marked as illegal when it should be legal\n{}", n, board);
             }
         }
     }
@@ -297,25 +278,88 @@ mod tests {
             false, false, true, false,
         ];
 
-        let board: Board = Board {
-            board: current_board.clone(),
-            size: 5,
-            player: Player::Black,
-            komi: 7.5,
-            opponent_passed: false,
-        };
+        let board: Board = Board::new(current_board.clone(), 5, Player::Black, 7.5, false);
 
         let sadly_self_capture = is_self_capture(10, &board);
         assert_eq!(sadly_self_capture, true, "Sadly a3 is self capture");
 
         let board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher::default());
         let legal_moves: BitSet = get_legal_moves(&board, &board_history);
+        assert_eq!(BitBoard::from(&board).legal_moves(&board_history), legal_moves, "The bitboard backend disagrees");
         for n in 0..25 as usize {
             if legal_moves.contains(n) {
-                assert_eq!(legality[n], true, "Move {} marked as legal when it should be illegal", n);
+                assert_eq!(legality[n], true, "Move {} marked as legal when it should be illegal\n{}", n, board);
             } else {
-                assert_eq!(false, legality[n], "Move {} marked as illegal when it should be legal", n);
+                assert_eq!(false, legality[n], "Move {} marked as illegal when it should be legal\n{}", n, board);
             }
         }
     }
+
+    #[test]
+    fn nine_by_nine_legality() {
+        let board: Box<[u8]> = board_from_string(
+            "
+    ......O.O
+    .......O.
+    .........
+    .........
+    .........
+    .........
+    .........
+    .........
+    .........
+    ",
+            9,
+        );
+        let board: Board = Board::new(board, 9, Player::Black, 7.5, false);
+        let board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher::default());
+        let legal_moves: BitSet = get_legal_moves_strict(&board, &board_history);
+        assert_eq!(BitBoard::from(&board).legal_moves_strict(&board_history), legal_moves, "The bitboard backend disagrees");
+
+        assert_eq!(legal_moves.contains(79), false, "Black playing between the white stones is self capture");
+        assert_eq!(legal_moves.contains(71), true, "Black can put the corner stone in atari");
+        assert_eq!(legal_moves.contains(0), true);
+        assert_eq!(legal_moves.len(), 77);
+        assert_eq!(captures_enemy_group(71, &board), false);
+
+        let after_atari: Board = make_move(Point::from_index(71, 81), &board);
+        let after_response: Board = make_move(Point::from_index(1, 81), &after_atari);
+        assert_eq!(captures_enemy_group(79, &after_response), true, "Filling the last liberty captures the corner stone");
+    }
+
+    #[test]
+    fn superko_capture_with_spare_liberty() {
+        let previous_board: Box<[u8]> = board_from_string(
+            "
+    X.X.X
+    .XOX.
+    XXOOX
+    XXO.O
+    .O.O.
+    ",
+            5,
+        );
+        let current_board: Box<[u8]> = board_from_string(
+            "
+    X.X.X
+    .XOX.
+    XXOOX
+    XXO.O
+    ..XO.
+    ",
+            5,
+        );
+        let board: Board = Board::new(current_board, 5, Player::White, 5.5, false);
+        let mut board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher::default());
+        board_history.insert(zobrist_hash(&previous_board));
+
+        // Capturing at b1 recreates the previous board. The capturing stone having another liberty doesn't make it legal.
+        let legal_moves: BitSet = get_legal_moves(&board, &board_history);
+        assert_eq!(BitBoard::from(&board).legal_moves(&board_history), legal_moves, "The bitboard backend disagrees");
+        assert_eq!(legal_moves.contains(1), false, "Recapturing violates superko");
+        assert_eq!(legal_moves.contains(0), true);
+
+        let board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher::default());
+        assert_eq!(get_legal_moves(&board, &board_history).contains(1), true, "Without the history it's a normal capture");
+    }
 }
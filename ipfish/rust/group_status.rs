@@ -0,0 +1,185 @@
+use rand::SeedableRng;
+use wasm_bindgen::prelude::*;
+
+use crate::RNG;
+use crate::benson::benson;
+use crate::bitboard::BitBoard;
+use crate::bitset::BitSet;
+use crate::board::{Board, BoardHistory, Chain};
+use crate::clock::Deadline;
+use crate::is_in_atari::is_in_atari;
+use crate::ownership::{Ownership, montecarlo_ownership};
+use crate::player::Player;
+use crate::tactics::{Tactics, read_chain};
+use crate::{board_from_js, set_panic_hook};
+
+// The ownership is measured with at least this many playouts, even if that takes longer than the budget.
+const MIN_OWNERSHIP_PLAYOUTS: u32 = 50;
+// The playouts are played in batches of this many.
+const OWNERSHIP_BATCH_SIZE: u32 = 10;
+// The share of the budget spent on the ownership playouts, the rest is left for reading out the chains.
+const OWNERSHIP_SHARE: f64 = 0.75;
+// A chain whose stones count for its owner in more than this share of the playouts is alive, in less than one minus it dead.
+const ALIVE_OWNERSHIP: f64 = 0.8;
+
+/// Whether a chain lives.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LifeStatus {
+    Alive,
+    Dead,
+    /// It depends on who gets to play there first.
+    Unsettled,
+}
+
+/// The status of a chain on the board.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct GroupStatus {
+    /// The PointState of the chain's stones, black or white.
+    pub color: u8,
+    /// The points of the stones in the chain.
+    pub stones: Vec<u32>,
+    /// The empty points adjacent to the chain.
+    pub liberties: Vec<u32>,
+    /// True if the chain has only one liberty left.
+    pub in_atari: bool,
+    pub status: LifeStatus,
+    /// The share of the playouts the chain's stones counted for its owner in.
+    pub ownership: f64,
+}
+
+/// Works out which chains on the board are alive, dead or unsettled, for highlighting weak groups.
+/// Takes the same arguments as get_analysis, and returns the status of every chain.
+///
+/// # Arguments
+///
+/// * `board_history` - All states the board has historically been in. The last element of the array is the current board position.
+/// * `komi` - The extra points white gets for the final score.
+/// * `turn` - Whether it's black or white's turn to play.
+/// * `opponent_passed` - Whether the opponent passed last turn.
+/// * `budget_ms` - How long to spend on playouts for the ownership and reading out the chains, in milliseconds.
+///
+/// Throws an error saying what's wrong if the arguments don't make up a valid board, like get_analysis.
+#[wasm_bindgen]
+pub fn get_group_status(
+    input_history: &js_sys::Array,
+    komi: &js_sys::Number,
+    turn: &js_sys::Number,
+    opponent_passed: &js_sys::Boolean,
+    budget_ms: &js_sys::Number,
+) -> Result<Vec<GroupStatus>, JsError> {
+    let deadline: Deadline = Deadline::after(budget_ms.value_of());
+
+    set_panic_hook();
+
+    let (board, board_history): (Board, BoardHistory) = board_from_js(input_history, komi, turn, opponent_passed)?;
+
+    let mut rng: RNG = RNG::seed_from_u64(js_sys::Math::random().to_bits());
+
+    let bitboard: BitBoard = BitBoard::from(&board);
+    let ownership_deadline: Deadline = deadline.fraction(OWNERSHIP_SHARE);
+    let mut ownership: Ownership = montecarlo_ownership(&bitboard, &board_history, MIN_OWNERSHIP_PLAYOUTS, &mut rng);
+    while !ownership_deadline.expired() {
+        ownership.add(&montecarlo_ownership(&bitboard, &board_history, OWNERSHIP_BATCH_SIZE, &mut rng));
+    }
+    return Ok(group_status(&board, &board_history, &ownership, &deadline));
+}
+
+/// The status of every chain on the board, ordered by their lowest point.
+/// Chains Benson's algorithm finds unconditionally alive are alive. Otherwise the tactical reading decides if it's sure,
+/// and when it isn't, the ownership does. Chains the reading doesn't get to before the deadline are Unsettled.
+///
+/// # Arguments
+///
+/// * `board` - The board to find the chains of.
+/// * `board_history` - The board history used for superko, including the board itself.
+/// * `ownership` - The ownership from playouts of the board.
+/// * `deadline` - When to stop reading out the chains.
+pub fn group_status(board: &Board, board_history: &BoardHistory, ownership: &Ownership, deadline: &Deadline) -> Vec<GroupStatus> {
+    let mut alive_stones: BitSet = BitSet::new();
+    for player in [Player::Black, Player::White] {
+        for chain in benson(board, player).alive_chains {
+            alive_stones |= chain;
+        }
+    }
+
+    let mut result: Vec<GroupStatus> = Vec::new();
+    let mut seen: BitSet = BitSet::new();
+    for point in 0..board.board.len() {
+        if !board.has_stone(point) || seen.contains(point) {
+            continue;
+        }
+        let chain: Chain = *board.chain(point);
+        seen |= chain.stones;
+
+        let owner: Player = Player::from(board.board[point] as f64);
+        let chain_ownership: f64 = chain.stones.into_iter().map(|stone| ownership.share(owner, stone)).sum::<f64>() / chain.stones.len() as f64;
+        let status: LifeStatus = if alive_stones.contains(point) {
+            LifeStatus::Alive
+        } else {
+            match read_chain(board, board_history, point, deadline) {
+                Tactics::Captured => LifeStatus::Dead,
+                Tactics::Unsettled => LifeStatus::Unsettled,
+                Tactics::Safe if chain_ownership > ALIVE_OWNERSHIP => LifeStatus::Alive,
+                Tactics::Safe if chain_ownership < 1.0 - ALIVE_OWNERSHIP => LifeStatus::Dead,
+                Tactics::Safe => LifeStatus::Unsettled,
+            }
+        };
+        result.push(GroupStatus {
+            color: owner as u8,
+            stones: chain.stones.into_iter().map(|stone| stone as u32).collect(),
+            liberties: chain.liberties.into_iter().map(|liberty| liberty as u32).collect(),
+            // Every chain has a liberty, board_from_input rejects boards with chains that don't.
+            in_atari: is_in_atari(point, board, chain.liberties.first().expect("Chains have liberties")),
+            status: status,
+            ownership: chain_ownership,
+        });
+    }
+    return result;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board_from_string::board_from_string;
+    use rustc_hash::FxBuildHasher;
+    use std::collections::HashSet;
+
+    #[test]
+    fn chains_get_a_status() {
+        let board: Board = Board::new(
+            board_from_string(
+                "
+    .X.X.
+    XXXXX
+    .....
+    O....
+    X.O..
+    ",
+                5,
+            ),
+            5,
+            Player::Black,
+            5.5,
+            false,
+        );
+        let board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+        let ownership: Ownership = montecarlo_ownership(&BitBoard::from(&board), &board_history, 50, &mut RNG::seed_from_u64(19));
+        let groups: Vec<GroupStatus> = group_status(&board, &board_history, &ownership, &Deadline::never());
+
+        // The lone black stone in the corner can't get away, it's caught between the white stones.
+        assert_eq!(groups.len(), 4);
+        assert_eq!(groups[0].stones, vec![0]);
+        assert_eq!(groups[0].color, Player::Black as u8);
+        assert!(groups[0].in_atari);
+        assert_eq!(groups[0].status, LifeStatus::Dead);
+        assert_eq!(groups[1].stones, vec![2]);
+        assert_eq!(groups[1].liberties, vec![1, 3, 7]);
+        assert!(!groups[1].in_atari);
+        // Black's wall has two eyes.
+        assert_eq!(groups[3].stones, vec![15, 16, 17, 18, 19, 21, 23]);
+        assert_eq!(groups[3].status, LifeStatus::Alive);
+        assert_eq!(groups[3].ownership, 1.0);
+    }
+}
@@ -1,65 +1,124 @@
 #![allow(warnings)]
+pub mod benson;
+pub mod bitboard;
 pub mod bitset;
 pub mod board;
+pub mod board_display;
 pub mod board_from_string;
+pub mod board_input;
+pub mod clock;
+pub mod engine;
+pub mod engine_config;
+pub mod evaluator;
 pub mod final_score;
 pub mod get_adjacent_points;
 pub mod get_legal_moves;
+pub mod group_status;
 pub mod is_in_atari;
 pub mod make_move;
 pub mod mcts_strategy;
+pub mod minimax_ab_strategy;
 pub mod montecarlo_score;
+pub mod ownership;
 pub mod pass_move;
 pub mod pick_strategy;
 pub mod player;
+pub mod playout_board;
+pub mod point;
 pub mod point_state;
-pub mod minimax_ab_strategy;
+pub mod score_stats;
+pub mod sgf;
+pub mod strategy;
+pub mod tactics;
+pub mod zobrist;
 
 use core::f64;
 use rand::SeedableRng;
 use rand_pcg::Pcg64Mcg;
-use rustc_hash::FxBuildHasher;
-use std::collections::HashSet;
 use std::panic;
 use wasm_bindgen::prelude::*;
 
 use crate::board::{Board, BoardHistory};
+use crate::board_input::board_from_input;
+use crate::clock::Deadline;
+use crate::engine_config::EngineConfig;
+use crate::evaluator::RandomPlayouts;
 use crate::pick_strategy::pick_strategy;
-use crate::player::Player;
+use crate::strategy::Analysis;
 
 pub type RNG = Pcg64Mcg;
 
-/// Performs an analysis on a a ipvgo board. Higher number = better move.
+/// What get_analysis returns.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct BoardAnalysis {
+    /// The score of every point, and of passing after the last point. Higher number = better move.
+    pub scores: Vec<f64>,
+    /// For every point, from -1 if it's sure to end up white's, through 0 for undecided or neutral, to 1 if it's sure to end up black's.
+    /// Empty if no games were played out.
+    pub ownership: Vec<f64>,
+}
+
+/// Performs an analysis on a a ipvgo board, and maps out who's expected to own each point.
 ///
 /// # Arguments
 ///
 /// * `board_history` - All states the board has historically been in. The last element of the array is the current board position.
 /// * `komi` - The extra points white gets for the final score.
-/// * `turn` - Whether it's black or white's turn to play. Currently only black has been tested. Trying to analyze for white may crash or lead to bad moves.
+/// * `turn` - Whether it's black or white's turn to play. The analysis is for the player to move.
 /// * `opponent_passed` - Whether the opponent passed last turn. This has important implications for analyzing the value of passing.
+/// * `budget_ms` - How long to think for, in milliseconds. The analysis may take a little longer on huge boards.
+/// * `config` - How to analyze, EngineConfig's constructor gives the defaults.
+///
+/// Throws an error saying what's wrong if the arguments don't make up a valid board, see InputError.
 #[wasm_bindgen]
-pub fn get_analysis(input_history: &js_sys::Array, komi: &js_sys::Number, turn: &js_sys::Number, opponent_passed: &js_sys::Boolean) -> js_sys::Float64Array {
-    panic::set_hook(Box::new(|panic_info| {
-        wasm_bindgen::throw_str(format!("{}", panic_info).as_str());
-    }));
+pub fn get_analysis(
+    input_history: &js_sys::Array,
+    komi: &js_sys::Number,
+    turn: &js_sys::Number,
+    opponent_passed: &js_sys::Boolean,
+    budget_ms: &js_sys::Number,
+    config: &EngineConfig,
+) -> Result<BoardAnalysis, JsError> {
+    // Start the clock before anything else, setting up the board is part of the budget too.
+    let deadline: Deadline = Deadline::after(budget_ms.value_of());
 
-    let current_board: Box<[u8]> = js_sys::Uint8Array::new(&input_history.iter().last().unwrap()).to_vec().into_boxed_slice();
-    let mut board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher::default());
-    for board in input_history.iter() {
-        board_history.insert(js_sys::Uint8Array::new(&board).to_vec().into_boxed_slice());
-    }
+    set_panic_hook();
 
-    let board: Board = Board {
-        size: current_board.len().isqrt(),
-        board: current_board,
-        player: Player::from(turn.value_of()),
-        komi: komi.value_of(),
-        opponent_passed: false,
-    };
+    let (board, board_history): (Board, BoardHistory) = board_from_js(input_history, komi, turn, opponent_passed)?;
 
     let mut rng: RNG = RNG::seed_from_u64(js_sys::Math::random().to_bits());
 
-    let result: Vec<f64> = pick_strategy(board, board_history, opponent_passed.value_of(), &mut rng);
+    let evaluator: RandomPlayouts = RandomPlayouts {
+        simulation_count: config.mcts_simulations,
+    };
+    let result: Analysis = pick_strategy(&board, &board_history, config, &evaluator, None, deadline, &mut rng);
+
+    return Ok(BoardAnalysis {
+        scores: result.scores,
+        ownership: match result.ownership {
+            Some(ownership) => ownership.map(),
+            None => Vec::new(),
+        },
+    });
+}
+
+/// Makes panics throw a javascript exception with the panic message, rather than an unhelpful "unreachable".
+pub(crate) fn set_panic_hook() {
+    panic::set_hook(Box::new(|panic_info| {
+        wasm_bindgen::throw_str(format!("{}", panic_info).as_str());
+    }));
+}
 
-    return js_sys::Float64Array::from(result.as_slice());
+/// Reads the current board and the board history from the arguments javascript passes in.
+/// See get_analysis for what the arguments mean, and board_from_input for how they're checked.
+pub(crate) fn board_from_js(
+    input_history: &js_sys::Array,
+    komi: &js_sys::Number,
+    turn: &js_sys::Number,
+    opponent_passed: &js_sys::Boolean,
+) -> Result<(Board, BoardHistory), JsError> {
+    let boards: Vec<Vec<u8>> = input_history.iter().map(|board| js_sys::Uint8Array::new(&board).to_vec()).collect();
+    let result: (Board, BoardHistory) = board_from_input(boards, komi.value_of(), turn.value_of(), opponent_passed.value_of())?;
+    return Ok(result);
 }
@@ -1,8 +1,10 @@
 use crate::bitset::BitSet;
-use crate::board::Board;
+use crate::board::{Board, Chain, NO_CHAIN};
 use crate::get_adjacent_points::get_adjacent_points;
-use crate::is_in_atari::is_in_atari;
+use crate::pass_move::pass_move;
+use crate::point::{Move, Point};
 use crate::point_state::PointState;
+use crate::zobrist::zobrist_key;
 
 /// Takes a board state and a point, and returns a board state that is the state after the move has been made.
 /// Note this does not check legality, as its redundant, so its possible to place pieces over others and such.
@@ -10,33 +12,114 @@ use crate::point_state::PointState;
 ///
 /// * `point` - The point to make the move on.
 /// * `board` - The state of the board.
-pub fn make_move(point: usize, board: &Board) -> Board {
+pub fn make_move(point: Point, board: &Board) -> Board {
+    let point: usize = point.index();
     let mut new_board: Board = Board {
         board: board.board.clone(),
         size: board.size,
         player: !board.player,
         komi: board.komi,
         opponent_passed: false,
+        hash: board.hash ^ zobrist_key(point, board.player as u8),
+        chain_ids: board.chain_ids.clone(),
+        chains: board.chains.clone(),
     };
 
-    // Is there are adjacent enemy groups in atari, remove them.
-    // new_board.player is the enemy player.
+    // Place the stone, as a chain of its own for now.
+    new_board.board[point] = board.player as u8;
+    let mut new_chain: Chain = Chain {
+        stones: BitSet::new(),
+        liberties: BitSet::new(),
+    };
+    new_chain.stones.insert(point);
+
+    // The point is no longer a liberty of any adjacent chain.
+    // Friendly chains get merged into the new one, enemy chains without liberties left get captured.
+    let mut friendly_chains: Vec<u16> = Vec::new();
+    let mut captured_chains: Vec<u16> = Vec::new();
     for adjacent_point in get_adjacent_points(point, board) {
-        if new_board.board[adjacent_point] == new_board.player as u8 && is_in_atari(adjacent_point, &new_board, point, &mut BitSet::new()) {
-            remove_group(adjacent_point, &mut new_board);
+        let chain_id: u16 = new_board.chain_ids[adjacent_point];
+        if chain_id == NO_CHAIN {
+            if new_board.board[adjacent_point] == PointState::Empty as u8 {
+                new_chain.liberties.insert(adjacent_point);
+            }
+            continue;
+        }
+        new_board.chains[chain_id as usize].liberties.remove(point);
+        if new_board.board[adjacent_point] == board.player as u8 {
+            if !friendly_chains.contains(&chain_id) {
+                friendly_chains.push(chain_id);
+            }
+        } else if new_board.chains[chain_id as usize].liberties.empty() && !captured_chains.contains(&chain_id) {
+            captured_chains.push(chain_id);
         }
     }
-    // Place the stone.
-    new_board.board[point] = board.player as u8;
+
+    for chain_id in friendly_chains.iter() {
+        new_chain.stones |= new_board.chains[*chain_id as usize].stones;
+        new_chain.liberties |= new_board.chains[*chain_id as usize].liberties;
+    }
+    new_chain.liberties.remove(point);
+    let new_chain_id: u16 = new_board.chains.len() as u16;
+    for stone in new_chain.stones {
+        new_board.chain_ids[stone] = new_chain_id;
+    }
+    new_board.chains.push(new_chain);
+
+    // Is there are adjacent enemy groups without liberties, remove them.
+    for chain_id in captured_chains.iter() {
+        remove_group(*chain_id, &mut new_board);
+    }
+
+    // The merged and captured chains are now stale entries, clear them out.
+    let mut stale_chains: Vec<u16> = friendly_chains;
+    stale_chains.append(&mut captured_chains);
+    stale_chains.sort_unstable();
+    for chain_id in stale_chains.into_iter().rev() {
+        remove_chain_entry(chain_id, &mut new_board);
+    }
 
     return new_board;
 }
 
-fn remove_group(point: usize, board: &mut Board) {
-    board.board[point] = PointState::Empty as u8;
-    for adjacent_point in get_adjacent_points(point, board) {
-        if board.board[adjacent_point] == board.player as u8 {
-            remove_group(adjacent_point, board);
+/// Makes a move for whoever's turn it is, playing a stone with make_move or passing with pass_move.
+///
+/// # Arguments
+///
+/// * `played` - The move to make.
+/// * `board` - The state of the board.
+pub fn play_move(played: Move, board: &Board) -> Board {
+    return match played {
+        Move::Play(point) => make_move(point, board),
+        Move::Pass => pass_move(board),
+    };
+}
+
+/// Takes the stones of a chain off the board, giving their points back as liberties to the chains around them.
+fn remove_group(chain_id: u16, board: &mut Board) {
+    let stones: BitSet = board.chains[chain_id as usize].stones;
+    for stone in stones {
+        board.hash ^= zobrist_key(stone, board.board[stone]);
+        board.board[stone] = PointState::Empty as u8;
+        board.chain_ids[stone] = NO_CHAIN;
+    }
+    for stone in stones {
+        for adjacent_point in get_adjacent_points(stone, board) {
+            let adjacent_chain: u16 = board.chain_ids[adjacent_point];
+            if adjacent_chain != NO_CHAIN {
+                board.chains[adjacent_chain as usize].liberties.insert(stone);
+            }
+        }
+    }
+}
+
+/// Removes a chain from the chain list, by moving the last chain into its slot.
+/// Removing several chains has to be done in descending chain id order, or the ids get mixed up.
+fn remove_chain_entry(chain_id: u16, board: &mut Board) {
+    board.chains.swap_remove(chain_id as usize);
+    if (chain_id as usize) < board.chains.len() {
+        for stone in board.chains[chain_id as usize].stones {
+            board.chain_ids[stone] = chain_id;
         }
     }
 }
@@ -1,84 +1,291 @@
 use core::f64;
-use std::cell::Cell;
-use std::collections::HashMap;
-
-use rand::seq;
+use rustc_hash::{FxHashMap, FxHasher};
+use std::hash::{Hash, Hasher};
 
 use crate::RNG;
 use crate::bitset::BitSet;
 use crate::board::{Board, BoardHistory};
+use crate::clock::Deadline;
+use crate::evaluator::{Evaluation, Evaluator};
+use crate::final_score::final_score;
 use crate::get_legal_moves::{get_legal_moves, get_legal_moves_strict};
-use crate::make_move::make_move;
-use crate::montecarlo_score::montecarlo_score;
+use crate::make_move::play_move;
+use crate::montecarlo_score::PlayedPoints;
+use crate::ownership::Ownership;
+use crate::pass_move::pass_move;
 use crate::player::Player;
+use crate::point::{Move, moves_on};
+use crate::score_stats::ScoreStats;
+use crate::strategy::{Analysis, Strategy};
+
+// The exploration constant of UCB1. Win ratios are between 0 and 1, so this is the textbook value.
+const UCT_CONST: f64 = std::f64::consts::SQRT_2;
+
+// How much the priors of the evaluator count for, compared to the win ratio, in PUCT.
+const PUCT_CONST: f64 = 1.5;
 
-const UCT_CONST: f64 = 42.0;
+/// A move from a node, along with the results of every playout that went through it.
+#[derive(Clone, Debug)]
+pub struct Edge {
+    pub played: Move,
+    // The index of the node the move leads to, None if it hasn't been explored yet.
+    pub child: Option<usize>,
+    pub blackwins: f64,
+    pub whitewins: f64,
+    // All moves as first statistics, the results of every simulation below the node in which the player to move played the point.
+    pub rave_blackwins: f64,
+    pub rave_whitewins: f64,
+    // How promising the evaluator thought the move was, normalized over the moves of the node. None if it had no idea.
+    pub prior: Option<f64>,
+}
 
+// A position in the search. Different move orders that reach the same position share the same node,
+// so the node statistics include every playout that went through the position, whichever way it was reached.
 #[derive(Clone, Debug)]
 pub struct Node {
-    pub blackwins: Cell<f64>,
-    pub whitewins: Cell<f64>,
-    pub favored_child: Cell<Option<usize>>,
+    pub blackwins: f64,
+    pub whitewins: f64,
+    // The final score margins of the playouts that went through the position, from black's point of view.
+    pub scores: ScoreStats,
+    // The index into edges of the move we want to explore next.
+    pub favored_edge: Option<usize>,
     pub board: Board,
-    pub children: BitSet,
+    // True if the game ended with two consecutive passes. Such a node has no edges, and is scored with final_score.
+    pub game_over: bool,
+    // One edge per legal move, in the same order as the legal moves bitset, followed by an edge for passing.
+    pub edges: Vec<Edge>,
+}
+
+// The tree is stored as an arena of nodes, that link to each other by index. The root is the first node.
+// Because of transpositions it's really a directed acyclic graph, a node can be the child of several edges.
+#[derive(Clone, Debug)]
+pub struct MCTree {
+    pub nodes: Vec<Node>,
+    // Maps the transposition key of every node to its index.
+    pub transpositions: FxHashMap<u64, usize>,
+    // How the all moves as first statistics are blended into the regular ones, when picking moves to explore.
+    pub rave_schedule: RaveSchedule,
+    // The ownership of the games played out by the evaluations since the root became the root.
+    pub ownership: Ownership,
+}
+
+/// How much weight the all moves as first statistics of a move get, compared to its regular statistics.
+/// They pile up a lot faster than the regular statistics, but they're biased, so their weight goes down as the move gets visited.
+/// Visits are counted in simulations.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RaveSchedule {
+    /// Plain UCT, the all moves as first statistics are ignored.
+    Off,
+    /// β = sqrt(k / (3n + k)), where n is the number of visits of the move.
+    /// Both statistics get equal weight once the move has been visited k times.
+    Equivalence(f64),
+    /// β = ñ / (n + ñ + 4b²nñ), where ñ is the number of all moves as first visits. From Gelly and Silver.
+    /// b is the bias of the all moves as first statistics, the lower it is the longer they're trusted.
+    MinimumError(f64),
 }
 
-// The tree is represented by a hashmap, where the key is the move sequence of the position, and the value is the Node.
-pub type MCTree = HashMap<Vec<usize>, Node>;
+impl RaveSchedule {
+    /// The weight of the all moves as first statistics, between 0 and 1.
+    pub fn beta(&self, visits: f64, rave_visits: f64) -> f64 {
+        return match *self {
+            RaveSchedule::Off => 0.0,
+            RaveSchedule::Equivalence(k) => (k / (3.0 * visits + k)).sqrt(),
+            RaveSchedule::MinimumError(bias) => rave_visits / (visits + rave_visits + 4.0 * bias * bias * visits * rave_visits),
+        };
+    }
+}
+
+impl Default for RaveSchedule {
+    fn default() -> RaveSchedule {
+        return RaveSchedule::Equivalence(1000.0);
+    }
+}
+
+impl MCTree {
+    /// Returns the node reached by playing `played` from the node at `index`, if it has been explored.
+    pub fn child(&self, index: usize, played: Move) -> Option<&Node> {
+        for edge in self.nodes[index].edges.iter() {
+            if edge.played == played {
+                return edge.child.map(|child| &self.nodes[child]);
+            }
+        }
+        return None;
+    }
+
+    /// Turns the subtree reached by playing `played` from the root into a tree of its own, so the search can carry on after the move is made.
+    /// The rest of the tree is thrown away. Returns None if the move was never explored.
+    pub fn reroot(self, played: Move) -> Option<MCTree> {
+        let new_root: usize = self.nodes[0].edges.iter().find(|edge| edge.played == played)?.child?;
 
-/// Generates a Monte Carlo Search Tree. Returns the tree itself.
+        // Find every node that's still reachable, in breadth first order so the new root comes first.
+        let mut new_indexes: Vec<Option<usize>> = vec![None; self.nodes.len()];
+        let mut kept: Vec<usize> = vec![new_root];
+        new_indexes[new_root] = Some(0);
+        let mut next: usize = 0;
+        while next < kept.len() {
+            for edge in self.nodes[kept[next]].edges.iter() {
+                if let Some(child) = edge.child {
+                    if new_indexes[child].is_none() {
+                        new_indexes[child] = Some(kept.len());
+                        kept.push(child);
+                    }
+                }
+            }
+            next += 1;
+        }
+
+        let mut old_nodes: Vec<Option<Node>> = self.nodes.into_iter().map(Some).collect();
+        let nodes: Vec<Node> = kept
+            .iter()
+            .map(|old_index| {
+                let mut node: Node = old_nodes[*old_index].take().expect("Every node is kept once");
+                for edge in node.edges.iter_mut() {
+                    edge.child = edge.child.map(|child| new_indexes[child].expect("Children of kept nodes are kept"));
+                }
+                return node;
+            })
+            .collect();
+        let transpositions: FxHashMap<u64, usize> = self
+            .transpositions
+            .into_iter()
+            .filter_map(|(key, old_index)| new_indexes[old_index].map(|new_index| (key, new_index)))
+            .collect();
+
+        return Some(MCTree {
+            // The ownership isn't kept per node, so it starts over from the new root.
+            ownership: Ownership::new(nodes[0].board.board.len()),
+            nodes: nodes,
+            transpositions: transpositions,
+            rave_schedule: self.rave_schedule,
+        });
+    }
+}
+
+/// Monte Carlo Tree Search as a Strategy.
+pub struct MctsStrategy<'a> {
+    /// Judges the leaf nodes of the tree.
+    pub evaluator: &'a dyn Evaluator,
+    /// A tree rooted at the board to carry on searching, if there is one. Otherwise the search starts from scratch.
+    pub tree: Option<&'a mut MCTree>,
+    /// How a tree started from scratch blends in the all moves as first statistics. A given tree keeps its own.
+    pub rave_schedule: RaveSchedule,
+}
+
+impl Strategy for MctsStrategy<'_> {
+    fn analyze(&mut self, board: &Board, board_history: &BoardHistory, deadline: Deadline, rng: &mut RNG) -> Analysis {
+        return match self.tree.as_deref_mut() {
+            Some(tree) => mcts_search(tree, board_history, self.evaluator, deadline, rng),
+            None => mcts_strategy(board.clone(), board_history.clone(), self.evaluator, self.rave_schedule, deadline, rng),
+        };
+    }
+}
+
+/// Generates a Monte Carlo Search Tree, and returns the analysis of the moves at the head of it.
 /// # Arguments
 ///
 /// * `board` - The board state to have at the head of the search tree. Takes ownership of it.
 /// * `board_history` - The board history used for superko.
-/// * `rng` - RNG used for MC playouts.
-pub fn mcts_strategy(board: Board, board_history: BoardHistory, rng: &mut RNG) -> Vec<f64> {
-    // The number of playouts to do at a time when doing evaluations.
-    let simulation_batch_size: u32 = 25;
-
-    // The number of playouts to do in total.
-    let playout_count: u32 = 65000;
-
-    // The number of playout batches to run.
-    let playout_batches: u32 = playout_count / simulation_batch_size;
+/// * `evaluator` - Judges the leaf nodes of the tree.
+/// * `rave_schedule` - How to blend in the all moves as first statistics.
+/// * `deadline` - When to stop searching. Every legal move gets explored at least once, even if that takes longer.
+/// * `rng` - RNG used for the evaluations.
+pub fn mcts_strategy(
+    board: Board,
+    board_history: BoardHistory,
+    evaluator: &dyn Evaluator,
+    rave_schedule: RaveSchedule,
+    deadline: Deadline,
+    rng: &mut RNG,
+) -> Analysis {
+    let mut tree: MCTree = initialize_tree(board, &board_history, evaluator, rave_schedule, rng);
+    return mcts_search(&mut tree, &board_history, evaluator, deadline, rng);
+}
 
-    let legal_moves: BitSet = get_legal_moves_strict(&board, &board_history);
-    let mut result: Vec<f64> = vec![f64::NEG_INFINITY; board.board.len() + 1];
-    let pass_move = board.board.len();
+/// Grows an existing Monte Carlo Search Tree until the deadline, and returns the analysis of the moves at the head of it.
+/// The tree can come from an earlier search, all the playouts that already went into it count towards the analysis.
+/// # Arguments
+///
+/// * `tree` - The tree to search, its root is the board to analyze.
+/// * `board_history` - The board history used for superko, including the board at the root.
+/// * `evaluator` - Judges the leaf nodes of the tree. Should be the same one the tree was grown with so far.
+/// * `deadline` - When to stop searching. Every legal move gets explored at least once, even if that takes longer.
+/// * `rng` - RNG used for the evaluations.
+pub fn mcts_search(tree: &mut MCTree, board_history: &BoardHistory, evaluator: &dyn Evaluator, deadline: Deadline, rng: &mut RNG) -> Analysis {
+    while !deadline.expired() || tree.nodes[0].edges.iter().any(|edge| edge.child.is_none()) {
+        mcts_playout(tree, board_history, evaluator, rng);
+    }
+    return mcts_analysis(tree, board_history);
+}
 
-    let mut tree: MCTree = initialize_tree(board, &board_history);
-    for _ in 0..playout_batches {
-        mcts_playout(&mut tree, &board_history, simulation_batch_size, rng);
+/// Grows an existing Monte Carlo Search Tree by a fixed number of iterations.
+/// Each iteration goes down the tree once and evaluates the leaf it ends up at, which plays out as many games as the evaluator does.
+/// Used for searching in small steps, so the caller can look at the analysis in between, or stop whenever.
+/// # Arguments
+///
+/// * `tree` - The tree to search.
+/// * `board_history` - The board history used for superko, including the board at the root.
+/// * `evaluator` - Judges the leaf nodes of the tree.
+/// * `iteration_count` - The number of iterations to do.
+/// * `rng` - RNG used for the evaluations.
+pub fn mcts_step(tree: &mut MCTree, board_history: &BoardHistory, evaluator: &dyn Evaluator, iteration_count: u32, rng: &mut RNG) {
+    for _ in 0..iteration_count {
+        mcts_playout(tree, board_history, evaluator, rng);
     }
+}
 
-    // Be pessimistic. Look at white's best response.
-    for point in legal_moves {
+/// Returns the analysis of the moves at the root of the tree, as it stands.
+/// Legal moves that haven't been explored yet are scored -infinity.
+/// Both are pessimistic, they look at the opponent's best response. The score of a move is the lowest win ratio, minus 0.5,
+/// of the move and the replies to it, and its score lead the lowest mean final score of them, for the player to move at the root.
+/// # Arguments
+///
+/// * `tree` - The tree to get the analysis of.
+/// * `board_history` - The board history used for superko, including the board at the root.
+pub fn mcts_analysis(tree: &MCTree, board_history: &BoardHistory) -> Analysis {
+    let board: &Board = &tree.nodes[0].board;
+    let legal_moves: BitSet = get_legal_moves_strict(board, board_history);
+    let mut result: Analysis = Analysis::new(board);
+    let player: Player = board.player;
+    // Moves that weren't looked at don't have a score lead, but the tree estimates it for the rest.
+    result.score_leads = Some(vec![f64::NAN; result.scores.len()]);
+
+    // Be pessimistic. Look at the opponent's best response.
+    for played in moves_on(legal_moves, board.board.len()).chain([Move::Pass]) {
         let mut score: f64 = f64::INFINITY;
-        match tree.get([point].as_slice()) {
-            None => panic!("No analysis found for legal move {}", point),
+        let mut lead: f64 = f64::INFINITY;
+        match tree.child(0, played) {
+            None => continue,
             Some(node) => {
-                let average_score: f64 = node.blackwins.get() / (node.blackwins.get() + node.whitewins.get()) - 0.5;
+                if node.scores.weight > 0.0 {
+                    lead = lead.min(player.score_for(node.scores.mean));
+                }
+                let average_score: f64 = win_ratio_for(player, node.blackwins, node.whitewins) - 0.5;
                 score = score.min(average_score);
-                for response in node.children {
-                    match tree.get([point, response].as_slice()) {
+                for response in node.edges.iter() {
+                    match response.child {
                         None => {
                             continue;
-                        },
-                        Some(s) => {
-                            let winrate: f64 = s.blackwins.get() / (s.whitewins.get() + s.blackwins.get()) - 0.5;
+                        }
+                        Some(child) => {
+                            let s: &Node = &tree.nodes[child];
+                            let winrate: f64 = win_ratio_for(player, s.blackwins, s.whitewins) - 0.5;
                             score = score.min(winrate);
+                            if s.scores.weight > 0.0 {
+                                lead = lead.min(player.score_for(s.scores.mean));
+                            }
                         }
                     }
                 }
             }
         }
-        result[point] = score;
+        result.set_score(played, score);
+        if lead.is_finite() {
+            result.set_score_lead(played, lead);
+        }
+    }
+    if tree.ownership.playouts > 0 {
+        result.ownership = Some(tree.ownership.clone());
     }
-
-    // Passing is not supported with this strategy.
-    result[pass_move] = f64::NEG_INFINITY;
-
-    // panic!("{:?}", tree);
 
     return result;
 }
@@ -88,21 +295,102 @@ pub fn mcts_strategy(board: Board, board_history: BoardHistory, rng: &mut RNG) -
 /// # Arguments
 ///
 /// * `board` - The board state to have at the head of the search tree. Takes ownership of it.
-fn initialize_tree(board: Board, board_history: &BoardHistory) -> MCTree {
-    let mut result: MCTree = HashMap::new();
-
+/// * `board_history` - The board history used for superko.
+/// * `evaluator` - Gives the priors of the moves at the root, if it has any.
+/// * `rave_schedule` - How to blend in the all moves as first statistics.
+/// * `rng` - RNG used for the evaluation.
+pub fn initialize_tree(board: Board, board_history: &BoardHistory, evaluator: &dyn Evaluator, rave_schedule: RaveSchedule, rng: &mut RNG) -> MCTree {
     let legal_moves: BitSet = get_legal_moves_strict(&board, board_history);
+    let mut transpositions: FxHashMap<u64, usize> = FxHashMap::default();
+    transpositions.insert(transposition_key(&board, false, legal_moves), 0);
+    // Only the priors and the ownership are used, the root's statistics are the sum of the playouts through it.
+    let evaluation: Evaluation = evaluator.evaluate(&board, board_history, rng);
+    return MCTree {
+        ownership: evaluation.ownership.unwrap_or_else(|| Ownership::new(board.board.len())),
+        nodes: vec![new_node(board, false, legal_moves, 0.0, 0.0, ScoreStats::new(), evaluation.priors.as_deref())],
+        transpositions: transpositions,
+        rave_schedule: rave_schedule,
+    };
+}
 
-    let root: Node = Node {
-        blackwins: Cell::new(0.0),
-        whitewins: Cell::new(0.0),
-        favored_child: Cell::new(legal_moves.first()),
-        children: legal_moves,
+/// Identifies a node for transposition detection.
+/// Besides the position itself, the key includes the legal moves. The same position can have different legal moves
+/// depending on the board history along the way there, due to superko, and those must stay separate nodes.
+///
+/// # Arguments
+///
+/// * `board` - The board state of the node.
+/// * `game_over` - True if the node is the end of the game.
+/// * `legal_moves` - The legal moves of the node, given the board history of the path that reached it.
+fn transposition_key(board: &Board, game_over: bool, legal_moves: BitSet) -> u64 {
+    let mut hasher: FxHasher = FxHasher::default();
+    board.hash.hash(&mut hasher);
+    board.player.hash(&mut hasher);
+    board.opponent_passed.hash(&mut hasher);
+    game_over.hash(&mut hasher);
+    legal_moves.hash(&mut hasher);
+    return hasher.finish();
+}
+
+/// Creates a node with unexplored edges for the legal moves and passing.
+/// The priors are indexed by point, with passing last. They're normalized over the moves of the node.
+fn new_node(board: Board, game_over: bool, legal_moves: BitSet, blackwins: f64, whitewins: f64, scores: ScoreStats, priors: Option<&[f64]>) -> Node {
+    // Once the game is over there's nothing left to play, not even a pass.
+    let point_count: usize = board.board.len();
+    let moves: Vec<Move> = match game_over {
+        true => Vec::new(),
+        false => moves_on(legal_moves, point_count).chain([Move::Pass]).collect(),
+    };
+    let prior_total: f64 = priors.map_or(0.0, |priors| moves.iter().map(|played| priors[played.index(point_count)]).sum());
+    let edges: Vec<Edge> = moves
+        .iter()
+        .map(|played| Edge {
+            played: *played,
+            child: None,
+            blackwins: 0.0,
+            whitewins: 0.0,
+            rave_blackwins: 0.0,
+            rave_whitewins: 0.0,
+            prior: priors.map(|priors| {
+                if prior_total > 0.0 {
+                    priors[played.index(point_count)] / prior_total
+                } else {
+                    1.0 / moves.len() as f64
+                }
+            }),
+        })
+        .collect();
+    // Without priors the moves are explored in order. With them the most promising one goes first.
+    let mut favored_edge: Option<usize> = if edges.is_empty() { None } else { Some(0) };
+    for (index, edge) in edges.iter().enumerate() {
+        if edge.prior > edges[favored_edge.unwrap()].prior {
+            favored_edge = Some(index);
+        }
+    }
+    return Node {
+        blackwins: blackwins,
+        whitewins: whitewins,
+        scores: scores,
+        favored_edge: favored_edge,
         board: board,
+        game_over: game_over,
+        edges: edges,
     };
+}
 
-    result.insert(Vec::new(), root);
-    return result;
+/// Evaluates a leaf node. Finished games are scored exactly, the rest by the evaluator.
+fn evaluate(board: &Board, game_over: bool, board_history: &BoardHistory, evaluator: &dyn Evaluator, rng: &mut RNG) -> Evaluation {
+    if game_over {
+        let score: f64 = final_score(board);
+        return Evaluation {
+            value: if score > 0.0 { 1.0 } else { 0.0 },
+            score: ScoreStats::single(score, evaluator.weight()),
+            priors: None,
+            amaf: None,
+            ownership: None,
+        };
+    }
+    return evaluator.evaluate(board, board_history, rng);
 }
 
 /// Does a playout on the Monte Carlo Search Tree, updating the tree with the results.
@@ -110,109 +398,576 @@ fn initialize_tree(board: Board, board_history: &BoardHistory) -> MCTree {
 ///
 /// * `tree` - The tree to do the playout on.
 /// * `board_history` - The historical board states, used for superko.
-/// * `playout_count` - The number of MC playouts to do on leaf nodes. We do actual MC playouts because no neural network.
-fn mcts_playout(tree: &mut MCTree, board_history: &BoardHistory, simulation_count: u32, rng: &mut RNG) {
-    // This returns a sequences to a not yet existing leaf.
-    let mut sequence: Vec<usize> = get_favorite_sequence(tree);
+/// * `evaluator` - Judges the leaf node. Its value counts for as many games as its weight.
+fn mcts_playout(tree: &mut MCTree, board_history: &BoardHistory, evaluator: &dyn Evaluator, rng: &mut RNG) {
+    // The nodes and edges taken from the root. A node can have several parents, so this is how we find our way back up.
+    let mut path: Vec<(usize, usize)> = get_favorite_path(tree);
+    // How many games every evaluation counts for.
+    let weight: f64 = evaluator.weight();
+
+    // The positions along the path are part of the board history as far as the leaf is concerned.
+    let mut leaf_history: BoardHistory = board_history.clone();
+    for (node_index, _) in path.iter() {
+        leaf_history.insert(tree.nodes[*node_index].board.hash);
+    }
+
+    let leaf_index: usize;
+    let evaluation: Evaluation;
     let leaf_blackwins: f64;
     let leaf_whitewins: f64;
-    match tree.get(&sequence) {
-        // This is the usual case.
-        None => {
-            let favored_move: usize = sequence.pop().expect("Somehow the favored sequence was empty?");
-            let parent_node: &Node = tree.get(&sequence).expect("Somehow the favored sequence doesn't have a parent?");
-            let new_board: Board = make_move(favored_move, &parent_node.board);
-            leaf_blackwins = montecarlo_score(&new_board, board_history, simulation_count, rng) as f64;
-            leaf_whitewins = simulation_count as f64 - leaf_blackwins;
-            let leaf_children: BitSet = get_legal_moves(&new_board, &board_history);
-            let leaf: Node = Node {
-                blackwins: Cell::new(leaf_blackwins),
-                whitewins: Cell::new(leaf_whitewins),
-                favored_child: Cell::new(leaf_children.first()),
-                board: new_board,
-                children: leaf_children,
+    let last_step: Option<(usize, usize)> = path.last().copied();
+    match last_step.filter(|(node_index, edge_index)| tree.nodes[*node_index].edges[*edge_index].child.is_none()) {
+        // This is the usual case, the favored move hasn't been explored yet.
+        Some((node_index, edge_index)) => {
+            let parent_board: &Board = &tree.nodes[node_index].board;
+            let favored_move: Move = tree.nodes[node_index].edges[edge_index].played;
+            // Two passes in a row and the game is over.
+            let game_over: bool = favored_move == Move::Pass && parent_board.opponent_passed;
+            let new_board: Board = play_move(favored_move, parent_board);
+            let leaf_children: BitSet = if game_over {
+                BitSet::new()
+            } else {
+                get_legal_moves(&new_board, &leaf_history)
             };
-            sequence.push(favored_move);
-            tree.insert(sequence.clone(), leaf);
+            let key: u64 = transposition_key(&new_board, game_over, leaf_children);
+            evaluation = evaluate(&new_board, game_over, &leaf_history, evaluator, rng);
+            leaf_blackwins = evaluation.value * weight;
+            leaf_whitewins = (1.0 - evaluation.value) * weight;
+            match tree.transpositions.get(&key) {
+                // A transposition, we've already got a node for this position from another move order.
+                Some(existing_index) => {
+                    leaf_index = *existing_index;
+                    tree.nodes[node_index].edges[edge_index].child = Some(leaf_index);
+                    tree.nodes[leaf_index].blackwins += leaf_blackwins;
+                    tree.nodes[leaf_index].whitewins += leaf_whitewins;
+                    tree.nodes[leaf_index].scores.merge(&evaluation.score);
+                }
+                None => {
+                    leaf_index = tree.nodes.len();
+                    tree.nodes.push(new_node(
+                        new_board,
+                        game_over,
+                        leaf_children,
+                        leaf_blackwins,
+                        leaf_whitewins,
+                        evaluation.score,
+                        evaluation.priors.as_deref(),
+                    ));
+                    tree.nodes[node_index].edges[edge_index].child = Some(leaf_index);
+                    tree.transpositions.insert(key, leaf_index);
+                }
+            }
         }
-        // Happens in endgame, when there's no legal followup moves.
-        // We just do another simulation cause why not.
-        Some(s) => {
-            assert!(s.favored_child.get().is_none());
-            let mc_wins: f64 = montecarlo_score(&s.board, board_history, simulation_count, rng) as f64;
-            leaf_blackwins = s.blackwins.get() + mc_wins;
-            leaf_whitewins = s.whitewins.get() + simulation_count as f64 - mc_wins;
-            s.blackwins.set(leaf_blackwins);
-            s.whitewins.set(leaf_whitewins);
+        // Happens when the game is over, there's nothing left to explore.
+        // We just score the final position again.
+        None => {
+            leaf_index = match last_step {
+                Some((node_index, edge_index)) => tree.nodes[node_index].edges[edge_index].child.expect("The path ends in an explored edge"),
+                None => 0,
+            };
+            let leaf: &mut Node = &mut tree.nodes[leaf_index];
+            evaluation = evaluate(&leaf.board, leaf.game_over, &leaf_history, evaluator, rng);
+            leaf_blackwins = evaluation.value * weight;
+            leaf_whitewins = (1.0 - evaluation.value) * weight;
+            leaf.blackwins += leaf_blackwins;
+            leaf.whitewins += leaf_whitewins;
+            leaf.scores.merge(&evaluation.score);
         }
     }
 
-    // Backpropegation of winrates and UCT scores.
-    while sequence.pop().is_some() {
-        let parent_node: &Node = tree.get(&sequence).expect("Parent node not found during mcts playout backpropegation!");
+    if let Some(ownership) = &evaluation.ownership {
+        tree.ownership.add(ownership);
+    }
+
+    // Backpropegation of winrates and UCT scores, back up along the path.
+    // The moves made on the path below the current node, for the all moves as first statistics.
+    let mut played_below: PlayedPoints = PlayedPoints::new();
+    while let Some((parent_index, edge_index)) = path.pop() {
+        let parent_node: &mut Node = &mut tree.nodes[parent_index];
+        let player: Player = parent_node.board.player;
 
         // Update wins.
-        parent_node.blackwins.set(parent_node.blackwins.get() + leaf_blackwins);
-        parent_node.whitewins.set(parent_node.whitewins.get() + leaf_whitewins);
+        parent_node.blackwins += leaf_blackwins;
+        parent_node.whitewins += leaf_whitewins;
+        parent_node.scores.merge(&evaluation.score);
+        parent_node.edges[edge_index].blackwins += leaf_blackwins;
+        parent_node.edges[edge_index].whitewins += leaf_whitewins;
+
+        // Update all moves as first wins. A move the player to move here made later on, in the tree or in a simulation,
+        // counts as if it was played now. Passing has no place on the board, so it doesn't get any.
+        // Moves made in the evaluation only count if the evaluator kept track of them.
+        if let Move::Play(taken_point) = parent_node.edges[edge_index].played {
+            played_below.insert(player, taken_point.index());
+        }
+        for edge in parent_node.edges.iter_mut() {
+            let Move::Play(point) = edge.played else {
+                continue;
+            };
+            let (rave_blackwins, rave_whitewins): (f64, f64) = if played_below.contains(player, point.index()) {
+                (leaf_blackwins, leaf_whitewins)
+            } else {
+                match &evaluation.amaf {
+                    Some(amaf) => amaf.wins(player, point.index()),
+                    None => (0.0, 0.0),
+                }
+            };
+            edge.rave_blackwins += rave_blackwins;
+            edge.rave_whitewins += rave_whitewins;
+        }
 
-        // Pick a new favored child based on UCT score.
+        // Pick a new favored child based on UCT score, or PUCT score if the evaluator gave priors.
+        let parent_node: &Node = &tree.nodes[parent_index];
+        let parent_visits: f64 = parent_node.blackwins + parent_node.whitewins;
+        let mut favored_edge: Option<usize> = None;
         let mut best_uct_score: f64 = f64::NEG_INFINITY;
-        for child in parent_node.children {
-            let mut child_sequence: Vec<usize> = sequence.clone();
-            child_sequence.push(child);
-            match tree.get(&child_sequence) {
-                // Unexplored children get top priority.
+        for (index, edge) in parent_node.edges.iter().enumerate() {
+            match edge.child {
+                // Unexplored children get top priority, unless the priors say otherwise.
                 None => {
-                    parent_node.favored_child.set(Some(child));
-                    break;
+                    let Some(prior) = edge.prior else {
+                        favored_edge = Some(index);
+                        break;
+                    };
+                    // An unexplored move is assumed to be as good as the position it's played from.
+                    let first_play_urgency: f64 = win_ratio_for(parent_node.board.player, parent_node.blackwins, parent_node.whitewins);
+                    let puct_score: f64 = puct_score(first_play_urgency, prior, parent_visits / weight, 0.0, PUCT_CONST);
+                    if puct_score > best_uct_score {
+                        best_uct_score = puct_score;
+                        favored_edge = Some(index);
+                    }
                 }
-                Some(s) => {
-                    let uct_score: f64 = uct_score(
-                        s.board.player,
-                        parent_node.blackwins.get(),
-                        parent_node.whitewins.get(),
-                        s.blackwins.get(),
-                        s.whitewins.get(),
-                        UCT_CONST,
-                    );
+                Some(child) => {
+                    // The win ratio comes from the child node, which is shared between move orders. It's the win ratio of the player
+                    // making the move, the player to move in the child is the opponent. How much this edge has been explored comes from the edge.
+                    let child_node: &Node = &tree.nodes[child];
+                    let edge_visits: f64 = edge.blackwins + edge.whitewins;
+                    let mut win_ratio: f64 = win_ratio_for(parent_node.board.player, child_node.blackwins, child_node.whitewins);
+                    let rave_visits: f64 = edge.rave_blackwins + edge.rave_whitewins;
+                    if rave_visits > 0.0 {
+                        let beta: f64 = tree.rave_schedule.beta(edge_visits, rave_visits);
+                        let rave_win_ratio: f64 = win_ratio_for(parent_node.board.player, edge.rave_blackwins, edge.rave_whitewins);
+                        win_ratio = (1.0 - beta) * win_ratio + beta * rave_win_ratio;
+                    }
+                    let uct_score: f64 = match edge.prior {
+                        Some(prior) => puct_score(win_ratio, prior, parent_visits / weight, edge_visits / weight, PUCT_CONST),
+                        None => uct_score(win_ratio, parent_visits, edge_visits, UCT_CONST),
+                    };
                     if uct_score > best_uct_score {
                         best_uct_score = uct_score;
-                        parent_node.favored_child.set(Some(child));
+                        favored_edge = Some(index);
                     }
                 }
             }
         }
+        tree.nodes[parent_index].favored_edge = favored_edge;
     }
 }
 
-/// https://www.chessprogramming.org/UCT
-fn uct_score(player: Player, parent_blackwins: f64, parent_whitewins: f64, child_blackwins: f64, child_whitewins: f64, uct_constant: f64) -> f64 {
-    let number_of_times_parent_has_been_visited: f64 = parent_blackwins + parent_whitewins;
-    let number_of_times_child_has_been_visited: f64 = child_blackwins + child_whitewins;
-    let win_ratio_of_child: f64 = match player {
-        Player::Black => child_blackwins / number_of_times_child_has_been_visited,
-        Player::White => child_whitewins / number_of_times_child_has_been_visited,
+/// The share of the wins that went to the player.
+fn win_ratio_for(player: Player, blackwins: f64, whitewins: f64) -> f64 {
+    return match player {
+        Player::Black => blackwins / (blackwins + whitewins),
+        Player::White => whitewins / (blackwins + whitewins),
     };
+}
+
+/// https://www.chessprogramming.org/UCT
+fn uct_score(win_ratio_of_child: f64, parent_visits: f64, edge_visits: f64, uct_constant: f64) -> f64 {
+    let number_of_times_parent_has_been_visited: f64 = parent_visits;
+    let number_of_times_child_has_been_visited: f64 = edge_visits;
 
     let part_under_sqrt: f64 = number_of_times_parent_has_been_visited.ln() / number_of_times_child_has_been_visited;
     return win_ratio_of_child + uct_constant * part_under_sqrt.sqrt();
 }
 
-/// Get the move sequence that we want to explore next, as per the algorithm.
-/// This will be a board position that we have not scored yet.
-/// Also known as a leaf node.
+/// https://www.chessprogramming.org/Christopher_D._Rosin#PUCT
+/// Visits are counted in evaluations rather than simulations, so the priors count for as much whatever the evaluator's weight is.
+fn puct_score(win_ratio_of_child: f64, prior: f64, parent_visits: f64, edge_visits: f64, puct_constant: f64) -> f64 {
+    return win_ratio_of_child + puct_constant * prior * parent_visits.sqrt() / (1.0 + edge_visits);
+}
+
+/// Get the path of nodes and moves that we want to explore next, as per the algorithm.
+/// The last move of the path leads to a board position that we have not scored yet, also known as a leaf node.
+/// If the path ends in a node where the game is over, that node itself is the leaf,
+/// and the last move of the path is the one leading to it. For the root that means an empty path.
 /// # Arguments
 ///
 /// * `tree` - The tree to get a leaf of.
-fn get_favorite_sequence(tree: &mut MCTree) -> Vec<usize> {
-    let mut sequence: Vec<usize> = Vec::new();
+fn get_favorite_path(tree: &MCTree) -> Vec<(usize, usize)> {
+    let mut path: Vec<(usize, usize)> = Vec::new();
+    let mut node_index: usize = 0;
     loop {
-        match tree.get(&sequence) {
-            Some(node) => match node.favored_child.get() {
-                Some(s) => sequence.push(s),
-                None => return sequence,
-            },
-            None => return sequence,
+        match tree.nodes[node_index].favored_edge {
+            Some(edge_index) => {
+                path.push((node_index, edge_index));
+                match tree.nodes[node_index].edges[edge_index].child {
+                    Some(child) => node_index = child,
+                    None => return path,
+                }
+            }
+            None => return path,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board_display::Heatmap;
+    use crate::board_from_string::board_from_string;
+    use crate::evaluator::{RandomPlayouts, TerritoryHeuristic};
+    use crate::point::Move;
+    use crate::point_state::PointState;
+    use crate::zobrist::zobrist_hash;
+    use rand::SeedableRng;
+    use rustc_hash::FxBuildHasher;
+    use std::collections::HashSet;
+
+    #[test]
+    fn tree_statistics_add_up() {
+        let board: Board = Board::new(vec![PointState::Empty as u8; 25].into_boxed_slice(), 5, Player::Black, 5.5, false);
+        let board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+        let mut rng: RNG = RNG::seed_from_u64(5);
+        let evaluator: RandomPlayouts = RandomPlayouts { simulation_count: 5 };
+
+        let mut tree: MCTree = initialize_tree(board, &board_history, &evaluator, RaveSchedule::default(), &mut rng);
+        for _ in 0..200 {
+            mcts_playout(&mut tree, &board_history, &evaluator, &mut rng);
+        }
+        assert_eq!(tree.nodes[0].blackwins + tree.nodes[0].whitewins, 1000.0);
+
+        // Every playout reaches a node through exactly one of its incoming edges.
+        let mut incoming_visits: Vec<f64> = vec![0.0; tree.nodes.len()];
+        for node in tree.nodes.iter() {
+            for edge in node.edges.iter() {
+                if let Some(child) = edge.child {
+                    incoming_visits[child] += edge.blackwins + edge.whitewins;
+                }
+            }
+        }
+        for (index, node) in tree.nodes.iter().enumerate().skip(1) {
+            let node_visits: f64 = node.blackwins + node.whitewins;
+            assert_eq!(node_visits, incoming_visits[index]);
+            // The rest of the visits are the node's own evaluations, of which there's at least one.
+            let edge_visits: f64 = node.edges.iter().map(|edge| edge.blackwins + edge.whitewins).sum();
+            assert!(node_visits - edge_visits >= 5.0);
+            assert_eq!((node_visits - edge_visits) % 5.0, 0.0);
+        }
+    }
+
+    #[test]
+    fn transpositions_share_a_node() {
+        let board: Board = Board::new(vec![PointState::Empty as u8; 9].into_boxed_slice(), 3, Player::Black, 5.5, false);
+        let board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+        let mut rng: RNG = RNG::seed_from_u64(6);
+        let evaluator: RandomPlayouts = RandomPlayouts { simulation_count: 1 };
+
+        let mut tree: MCTree = initialize_tree(board, &board_history, &evaluator, RaveSchedule::default(), &mut rng);
+        for _ in 0..700 {
+            mcts_playout(&mut tree, &board_history, &evaluator, &mut rng);
+        }
+
+        // Black 0, white 1, black 2 and black 2, white 1, black 0 reach the same position.
+        let index_of = |points: [usize; 3]| -> Option<usize> {
+            let mut index: usize = 0;
+            for point in points {
+                let edge: &Edge = tree.nodes[index].edges.iter().find(|edge| edge.played == Move::from_index(point, 9))?;
+                index = edge.child?;
+            }
+            return Some(index);
+        };
+        let forwards: usize = index_of([0, 1, 2]).expect("The line was explored");
+        let backwards: usize = index_of([2, 1, 0]).expect("The line was explored");
+        assert_eq!(forwards, backwards);
+        assert_eq!(tree.transpositions.len(), tree.nodes.len());
+    }
+
+    #[test]
+    fn superko_keeps_positions_apart() {
+        // The same position, reached with a board history that forbids retaking the ko and with one that doesn't.
+        let previous_board: Box<[u8]> = board_from_string(
+            "
+    .....
+    .....
+    .XO..
+    XO.O.
+    .XO..
+    ",
+            5,
+        );
+        let current_board: Box<[u8]> = board_from_string(
+            "
+    .....
+    .....
+    .XO..
+    X.XO.
+    .XO..
+    ",
+            5,
+        );
+        let board: Board = Board::new(current_board, 5, Player::White, 5.5, false);
+        let mut board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+        let unrestricted: BitSet = get_legal_moves(&board, &board_history);
+        board_history.insert(zobrist_hash(&previous_board));
+        let restricted: BitSet = get_legal_moves(&board, &board_history);
+
+        assert_ne!(unrestricted, restricted);
+        assert_ne!(transposition_key(&board, false, unrestricted), transposition_key(&board, false, restricted));
+    }
+
+    #[test]
+    fn passing_ends_the_game() {
+        // Black is ahead, and the board is settled.
+        let board: Board = Board::new(
+            board_from_string(
+                "
+    .X.X.
+    XXXXX
+    XXXXX
+    OOOOO
+    .O.O.
+    ",
+                5,
+            ),
+            5,
+            Player::Black,
+            0.5,
+            false,
+        );
+        let board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+        let mut rng: RNG = RNG::seed_from_u64(7);
+        let evaluator: RandomPlayouts = RandomPlayouts { simulation_count: 5 };
+
+        let mut tree: MCTree = initialize_tree(board, &board_history, &evaluator, RaveSchedule::default(), &mut rng);
+        for _ in 0..200 {
+            mcts_playout(&mut tree, &board_history, &evaluator, &mut rng);
+        }
+
+        // Black passing doesn't end the game, white gets to respond.
+        let black_pass: &Node = tree.child(0, Move::Pass).expect("Passing was explored");
+        assert_eq!(black_pass.game_over, false);
+        assert_eq!(black_pass.board.opponent_passed, true);
+        assert_eq!(black_pass.edges.last().unwrap().played, Move::Pass);
+
+        // White passing back does, and it's scored exactly.
+        let both_passed: &Node = tree
+            .nodes
+            .iter()
+            .find(|node| node.game_over && node.board.hash == black_pass.board.hash)
+            .expect("Passing back was explored");
+        assert_eq!(both_passed.edges.len(), 0);
+        assert_eq!(both_passed.whitewins, 0.0);
+        assert!(both_passed.blackwins > 0.0);
+    }
+
+    #[test]
+    fn passing_ends_the_game_for_white() {
+        // White is ahead, and the board is settled.
+        let board: Board = Board::new(
+            board_from_string(
+                "
+    .O.O.
+    OOOOO
+    OOOOO
+    XXXXX
+    .X.X.
+    ",
+                5,
+            ),
+            5,
+            Player::White,
+            0.5,
+            false,
+        );
+        let board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+        let mut rng: RNG = RNG::seed_from_u64(7);
+        let evaluator: RandomPlayouts = RandomPlayouts { simulation_count: 5 };
+
+        let mut tree: MCTree = initialize_tree(board, &board_history, &evaluator, RaveSchedule::default(), &mut rng);
+        for _ in 0..200 {
+            mcts_playout(&mut tree, &board_history, &evaluator, &mut rng);
+        }
+
+        let white_pass: &Node = tree.child(0, Move::Pass).expect("Passing was explored");
+        assert_eq!(white_pass.game_over, false);
+        assert_eq!(white_pass.board.player, Player::Black);
+        let both_passed: &Node = tree
+            .nodes
+            .iter()
+            .find(|node| node.game_over && node.board.hash == white_pass.board.hash)
+            .expect("Passing back was explored");
+        assert_eq!(both_passed.blackwins, 0.0);
+        assert!(both_passed.whitewins > 0.0);
+    }
+
+    #[test]
+    fn both_players_take_the_capture() {
+        // Whoever plays on the right of the white stones captures, and wins the game. The alternatives are filling eyes, or passing.
+        let black_board: Box<[u8]> = board_from_string(
+            "
+    XXXXX
+    X.X.X
+    XXXXX
+    OOOO.
+    XXXXX
+    ",
+            5,
+        );
+        // The same position with the colors the other way around.
+        let white_board: Box<[u8]> = board_from_string(
+            "
+    OOOOO
+    O.O.O
+    OOOOO
+    XXXX.
+    OOOOO
+    ",
+            5,
+        );
+        let capture: usize = 9;
+        let board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+        let evaluator: RandomPlayouts = RandomPlayouts { simulation_count: 5 };
+
+        for (board, player, komi) in [(black_board, Player::Black, 0.5), (white_board, Player::White, -0.5)] {
+            let mut rng: RNG = RNG::seed_from_u64(14);
+            let mut tree: MCTree = initialize_tree(
+                Board::new(board, 5, player, komi, false),
+                &board_history,
+                &evaluator,
+                RaveSchedule::default(),
+                &mut rng,
+            );
+            mcts_step(&mut tree, &board_history, &evaluator, 300, &mut rng);
+            let analysis: Analysis = mcts_analysis(&tree, &board_history);
+            assert_eq!(
+                analysis.best_move(),
+                Move::from_index(capture, 25),
+                "{:?} should capture, the analysis is\n{}",
+                player,
+                Heatmap::new(&tree.nodes[0].board, &analysis.scores)
+            );
+            assert!(analysis.scores[capture] > 0.0);
+            // The capture also wins by the most.
+            let score_leads: Vec<f64> = analysis.score_leads.expect("The tree keeps track of the scores");
+            assert!(score_leads[capture] > 0.0);
+            assert!(
+                score_leads.iter().filter(|lead| !lead.is_nan()).all(|lead| *lead <= score_leads[capture]),
+                "{:?}",
+                score_leads
+            );
+        }
+    }
+
+    #[test]
+    fn every_move_is_analyzed_when_out_of_time() {
+        let board: Board = Board::new(vec![PointState::Empty as u8; 25].into_boxed_slice(), 5, Player::Black, 5.5, false);
+        let board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+        let mut rng: RNG = RNG::seed_from_u64(8);
+
+        let result: Analysis = mcts_strategy(
+            board,
+            board_history,
+            &RandomPlayouts::default(),
+            RaveSchedule::default(),
+            Deadline::after(0.0),
+            &mut rng,
+        );
+        assert_eq!(result.scores.len(), 26);
+        for score in result.scores {
+            assert!(score.is_finite());
+        }
+    }
+
+    #[test]
+    fn rave_statistics_include_the_regular_ones() {
+        let board: Board = Board::new(vec![PointState::Empty as u8; 25].into_boxed_slice(), 5, Player::Black, 5.5, false);
+        let board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+        let mut rng: RNG = RNG::seed_from_u64(11);
+        let evaluator: RandomPlayouts = RandomPlayouts { simulation_count: 5 };
+
+        let mut tree: MCTree = initialize_tree(board, &board_history, &evaluator, RaveSchedule::default(), &mut rng);
+        for _ in 0..200 {
+            mcts_playout(&mut tree, &board_history, &evaluator, &mut rng);
+        }
+
+        // Every simulation through a move also counts for the move's all moves as first statistics.
+        for node in tree.nodes.iter() {
+            for edge in node.edges.iter() {
+                let rave_visits: f64 = edge.rave_blackwins + edge.rave_whitewins;
+                if edge.played == Move::Pass {
+                    assert_eq!(rave_visits, 0.0);
+                } else {
+                    assert!(rave_visits >= edge.blackwins + edge.whitewins);
+                }
+            }
+        }
+        // And the root moves get more from the simulations on top of that.
+        let root_visits: f64 = tree.nodes[0].edges.iter().map(|edge| edge.blackwins + edge.whitewins).sum();
+        let root_rave_visits: f64 = tree.nodes[0].edges.iter().map(|edge| edge.rave_blackwins + edge.rave_whitewins).sum();
+        assert!(root_rave_visits > 2.0 * root_visits);
+    }
+
+    #[test]
+    fn priors_guide_the_search() {
+        let board: Board = Board::new(
+            board_from_string(
+                "
+    .....
+    .X...
+    .....
+    ...O.
+    .....
+    ",
+                5,
+            ),
+            5,
+            Player::Black,
+            0.5,
+            false,
+        );
+        let board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+        let mut rng: RNG = RNG::seed_from_u64(12);
+
+        let mut tree: MCTree = initialize_tree(board, &board_history, &TerritoryHeuristic, RaveSchedule::default(), &mut rng);
+        let priors: Vec<f64> = tree.nodes[0]
+            .edges
+            .iter()
+            .map(|edge| edge.prior.expect("The territory heuristic has priors"))
+            .collect();
+        assert!((priors.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+
+        // The most promising move is explored first.
+        let highest_prior: f64 = priors.iter().cloned().fold(0.0, f64::max);
+        mcts_playout(&mut tree, &board_history, &TerritoryHeuristic, &mut rng);
+        let explored: Vec<&Edge> = tree.nodes[0].edges.iter().filter(|edge| edge.child.is_some()).collect();
+        assert_eq!(explored.len(), 1);
+        assert_eq!(explored[0].prior, Some(highest_prior));
+
+        // The most promising moves get the most attention.
+        for _ in 0..50 {
+            mcts_playout(&mut tree, &board_history, &TerritoryHeuristic, &mut rng);
+        }
+        // The territory heuristic's values aren't whole numbers, so the sums are a little off.
+        assert!((tree.nodes[0].blackwins + tree.nodes[0].whitewins - 51.0).abs() < 1e-9);
+        let visits = |prior: f64| -> f64 {
+            let edge: &Edge = tree.nodes[0].edges.iter().find(|edge| edge.prior == Some(prior)).unwrap();
+            return edge.blackwins + edge.whitewins;
+        };
+        let lowest_prior: f64 = priors.iter().cloned().fold(1.0, f64::min);
+        assert!(visits(highest_prior) > visits(lowest_prior));
+    }
+
+    #[test]
+    fn rave_schedules() {
+        assert_eq!(RaveSchedule::Off.beta(0.0, 100.0), 0.0);
+
+        let equivalence: RaveSchedule = RaveSchedule::Equivalence(1000.0);
+        assert_eq!(equivalence.beta(0.0, 100.0), 1.0);
+        assert_eq!(equivalence.beta(1000.0, 5000.0), 0.5);
+        assert!(equivalence.beta(100000.0, 500000.0) < 0.1);
+
+        let minimum_error: RaveSchedule = RaveSchedule::MinimumError(0.1);
+        assert_eq!(minimum_error.beta(0.0, 100.0), 1.0);
+        assert!(minimum_error.beta(100.0, 500.0) > minimum_error.beta(1000.0, 5000.0));
+    }
+}
@@ -1,49 +1,229 @@
 use core::f64;
+use rustc_hash::{FxHashMap, FxHasher};
+use std::hash::{Hash, Hasher};
 
+use crate::RNG;
+use crate::benson::{SettledPoints, settled_points};
 use crate::bitset::BitSet;
 use crate::board::{Board, BoardHistory};
-use crate::final_score::{SeenStones, final_score, score_group_territory};
+use crate::clock::Deadline;
+use crate::evaluator::Evaluator;
+use crate::final_score::final_score;
 use crate::get_legal_moves::{captures_enemy_group, get_legal_moves, get_legal_moves_strict};
-use crate::make_move::make_move;
+use crate::make_move::{make_move, play_move};
 use crate::pass_move::pass_move;
 use crate::player::Player;
-use crate::point_state::PointState;
+use crate::point::{Move, Point, moves_on};
+use crate::strategy::{Analysis, Strategy};
 
-/// This uses minimax with alpha beta pruning. For the scoring function it just uses the game result.
-/// This is good as a "finisher" and terrible at opening the game.
-/// It will try and maximize the result score.
-pub fn minimax_ab_strategy(board: &Board, board_history: &BoardHistory, opponent_passed: bool) -> Vec<f64> {
-    let minimax_depth: usize = 7;
+/// Minimax as a Strategy. Whether passing ends the game comes from the board.
+pub struct MinimaxAbStrategy<'a> {
+    /// Scores the positions at the bottom of the search.
+    pub evaluator: &'a dyn Evaluator,
+}
+
+impl Strategy for MinimaxAbStrategy<'_> {
+    fn analyze(&mut self, board: &Board, board_history: &BoardHistory, deadline: Deadline, rng: &mut RNG) -> Analysis {
+        return minimax_ab_strategy(board, board_history, board.opponent_passed, self.evaluator, deadline, rng);
+    }
+}
+
+// Stop adding positions to the transposition table once it has this many, so it can't eat all the memory on big boards.
+const TRANSPOSITION_TABLE_CAPACITY: usize = 1 << 20;
+
+// The number of killer moves remembered per ply.
+const KILLER_SLOTS: usize = 2;
+
+/// What a value in the transposition table means, alpha beta cutoffs leave some values as bounds of the true value.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Bound {
+    Exact,
+    // The true value is at least this much.
+    Lower,
+    // The true value is at most this much.
+    Upper,
+}
+
+/// What searching a position found out.
+#[derive(Clone, Copy, Debug)]
+struct TableEntry {
+    depth: usize,
+    value: f64,
+    bound: Bound,
+    // The move that got the value, None if it's the value of passing.
+    best_move: Option<usize>,
+}
+
+/// What the search learns as it goes, that helps the rest of the search and the deeper searches after it.
+struct SearchTables {
+    // Keyed by the table key of the position. The board history isn't part of the key,
+    // superko only rarely makes the same position play out differently and it's not worth the missed hits.
+    transpositions: FxHashMap<u64, TableEntry>,
+    // Quiet moves that recently caused a cutoff, per ply. They're likely to cause a cutoff in the other positions at that ply too.
+    killers: Vec<[Option<usize>; KILLER_SLOTS]>,
+}
+
+impl SearchTables {
+    fn new() -> SearchTables {
+        return SearchTables {
+            transpositions: FxHashMap::default(),
+            killers: Vec::new(),
+        };
+    }
+
+    fn store(&mut self, key: u64, entry: TableEntry) {
+        if self.transpositions.len() < TRANSPOSITION_TABLE_CAPACITY || self.transpositions.contains_key(&key) {
+            self.transpositions.insert(key, entry);
+        }
+    }
+
+    fn killers(&self, ply: usize) -> [Option<usize>; KILLER_SLOTS] {
+        return self.killers.get(ply).copied().unwrap_or([None; KILLER_SLOTS]);
+    }
+
+    fn add_killer(&mut self, ply: usize, point: usize) {
+        if self.killers.len() <= ply {
+            self.killers.resize(ply + 1, [None; KILLER_SLOTS]);
+        }
+        let killers: &mut [Option<usize>; KILLER_SLOTS] = &mut self.killers[ply];
+        if killers[0] != Some(point) {
+            killers.rotate_right(1);
+            killers[0] = Some(point);
+        }
+    }
+}
+
+/// This uses minimax with alpha beta pruning. The positions at the bottom of the search are scored by the evaluator.
+/// With the final score as the evaluator this is good as a "finisher" and terrible at opening the game.
+/// It will try and maximize the evaluation.
+/// The search is iterative deepening, it searches one move deeper at a time until the deadline,
+/// and returns the result of the deepest search that finished. The transposition table and killer moves are kept between
+/// the searches, so each search starts with the best moves of the one before.
+pub fn minimax_ab_strategy(
+    board: &Board,
+    board_history: &BoardHistory,
+    opponent_passed: bool,
+    evaluator: &dyn Evaluator,
+    deadline: Deadline,
+    rng: &mut RNG,
+) -> Analysis {
+    let mut tables: SearchTables = SearchTables::new();
+    // Neither player can unsettle these, so they're found once and stay pointless to play everywhere in the search.
+    let settled: SettledPoints = settled_points(board);
+
+    // A search of depth 1 is instant, and it means we always have something to return.
+    let mut result: Analysis = minimax_at_depth(
+        board,
+        board_history,
+        opponent_passed,
+        1,
+        &settled,
+        &mut tables,
+        evaluator,
+        &Deadline::never(),
+        rng,
+    )
+    .expect("A search without a deadline finishes");
+
+    // There's no point searching deeper than the number of points on the board, as it's unlikely to ever get there.
+    for minimax_depth in 2..=board.board.len() {
+        match minimax_at_depth(
+            board,
+            board_history,
+            opponent_passed,
+            minimax_depth,
+            &settled,
+            &mut tables,
+            evaluator,
+            &deadline,
+            rng,
+        ) {
+            Some(deeper_result) => result = deeper_result,
+            None => break,
+        }
+    }
+    return result;
+}
+
+/// Evaluates every move to a fixed depth. Returns None if the deadline passed before the search was finished.
+fn minimax_at_depth(
+    board: &Board,
+    board_history: &BoardHistory,
+    opponent_passed: bool,
+    minimax_depth: usize,
+    settled: &SettledPoints,
+    tables: &mut SearchTables,
+    evaluator: &dyn Evaluator,
+    deadline: &Deadline,
+    rng: &mut RNG,
+) -> Option<Analysis> {
     let alpha: f64 = f64::NEG_INFINITY;
     let beta: f64 = f64::INFINITY;
 
-    let mut result: Vec<f64> = vec![f64::NEG_INFINITY; board.board.len() + 1];
+    let mut result: Analysis = Analysis::new(board);
+    // The search goes by black's point of view, the analysis is for the player to move.
+    let player: Player = board.player;
 
-    for point in get_legal_moves_strict(board, board_history) {
-        result[point] = minimax_alphabeta(&make_move(point, board), board_history, minimax_depth, alpha, beta) + 10.0;
+    for played in moves_on(get_legal_moves_strict(board, board_history), board.board.len()) {
+        let minimax_score: f64 = minimax_alphabeta(
+            &play_move(played, board),
+            board_history,
+            minimax_depth,
+            0,
+            alpha,
+            beta,
+            settled,
+            tables,
+            evaluator,
+            deadline,
+            rng,
+        )?;
+        result.set_score(played, player.value_for(minimax_score) + 10.0);
     }
 
-    let result_score: f64 = final_score(board);
+    let result_score: f64 = player.score_for(final_score(board));
     match opponent_passed {
         false => {
             if result_score > 0.0 {
-                result[board.board.len()] = minimax_alphabeta(&pass_move(board), board_history, minimax_depth, alpha, beta) + 10.0;
+                let minimax_score: f64 = minimax_alphabeta(
+                    &pass_move(board),
+                    board_history,
+                    minimax_depth,
+                    0,
+                    alpha,
+                    beta,
+                    settled,
+                    tables,
+                    evaluator,
+                    deadline,
+                    rng,
+                )?;
+                result.set_score(Move::Pass, player.value_for(minimax_score) + 10.0);
             }
         }
         true => {
+            // Passing back ends the game, and we win. Nothing the evaluator says about a position is better than that.
             if result_score > 0.0 {
-                result[board.board.len()] = result_score + 10.0;
+                result.set_score(Move::Pass, 1.0 + 10.0);
             }
         }
     }
-    return result;
+    return Some(result);
 }
 
 /// Private function! This is the score according to the minimax algorithm.
 /// This is the value it's trying to minimize and maximize.
-/// Changing the scoring algorithm will significantly alter the behavior of the minimax algorithm.
-fn score(board: &Board) -> f64 {
-    return final_score(board);
+/// Changing the evaluator will significantly alter the behavior of the minimax algorithm.
+fn score(board: &Board, board_history: &BoardHistory, evaluator: &dyn Evaluator, rng: &mut RNG) -> f64 {
+    return evaluator.evaluate(board, board_history, rng).value;
+}
+
+/// Identifies a position in the transposition table.
+fn table_key(board: &Board) -> u64 {
+    let mut hasher: FxHasher = FxHasher::default();
+    board.hash.hash(&mut hasher);
+    board.player.hash(&mut hasher);
+    return hasher.finish();
 }
 
 /// Returns the evaluation of a board position using minimax algorithm to a specified depth.
@@ -55,78 +235,358 @@ fn score(board: &Board) -> f64 {
 /// * `board` - The board state to evaluate.
 /// * `board_history` - The board history of the current state.
 /// * `depth` - The maximum, or remaining, depth to search. 0 means to just score the current board.
+/// * `ply` - How many moves deep into the search the board is. 0 for non recursive calls.
 /// * `alpha` - The highest score seen so far. Pass -infinity for non recursive calls.
 /// * `beta` - The lower score seen so far. Pass +infinity for non recursive calls.
-fn minimax_alphabeta(board: &Board, board_history: &BoardHistory, depth: usize, mut alpha: f64, mut beta: f64) -> f64 {
+/// * `settled` - The settled points of the board the search started from, they're not worth playing on.
+/// * `tables` - The transposition table and killer moves, they're updated with what the search finds out.
+/// * `evaluator` - Scores the positions at the bottom of the search.
+/// * `deadline` - When to give up on the search, in which case None is returned.
+/// * `rng` - RNG for evaluators that need it.
+fn minimax_alphabeta(
+    board: &Board,
+    board_history: &BoardHistory,
+    depth: usize,
+    ply: usize,
+    mut alpha: f64,
+    mut beta: f64,
+    settled: &SettledPoints,
+    tables: &mut SearchTables,
+    evaluator: &dyn Evaluator,
+    deadline: &Deadline,
+    rng: &mut RNG,
+) -> Option<f64> {
     // Terminating condition
     if depth < 1 {
-        return score(board);
-    } else {
-        let mut deeper_history: BoardHistory = board_history.clone();
-        deeper_history.insert(board.board.clone());
+        return Some(score(board, board_history, evaluator, rng));
+    } else if deadline.expired() {
+        return None;
+    }
 
+    // A search at least as deep as this one may have already settled the value, or narrowed it down.
+    let key: u64 = table_key(board);
+    let entry: Option<TableEntry> = tables.transpositions.get(&key).copied();
+    if let Some(entry) = entry.filter(|entry| entry.depth >= depth) {
+        match entry.bound {
+            Bound::Exact => return Some(entry.value),
+            Bound::Lower => alpha = alpha.max(entry.value),
+            Bound::Upper => beta = beta.min(entry.value),
+        }
+        if beta <= alpha {
+            return Some(entry.value);
+        }
+    }
+    let (window_alpha, window_beta): (f64, f64) = (alpha, beta);
+
+    let mut deeper_history: BoardHistory = board_history.clone();
+    deeper_history.insert(board.hash);
+    let moves: Vec<usize> = ordered_moves(board, board_history, settled, entry.and_then(|entry| entry.best_move), tables.killers(ply));
+
+    // We start out with the current state of the board, as if we were to pass, and we want to find a move that improves that.
+    let mut best_score: f64 = score(board, board_history, evaluator, rng);
+    let mut best_move: Option<usize> = None;
+    for point in moves {
+        let minimax_score: f64 = minimax_alphabeta(
+            &make_move(Point::from_index(point, board.board.len()), board),
+            &deeper_history,
+            depth - 1,
+            ply + 1,
+            alpha,
+            beta,
+            settled,
+            tables,
+            evaluator,
+            deadline,
+            rng,
+        )?;
         if board.player == Player::Black {
-            // Maximizing
-            // We start out with the current state of the board, as if we were to pass, and we want to find a move that improves that.
-            let mut best_score: f64 = score(board);
-            for point in get_legal_moves(board, board_history) {
-                let minimax_score: f64 = minimax_alphabeta(&make_move(point, board), &deeper_history, depth - 1, alpha, beta);
-                // Maximizing.
-                best_score = best_score.max(minimax_score);
-                alpha = alpha.max(best_score);
-                if beta <= alpha {
-                    break;
-                }
+            // Maximizing.
+            if minimax_score > best_score {
+                best_score = minimax_score;
+                best_move = Some(point);
             }
-            return best_score;
+            alpha = alpha.max(best_score);
         } else {
             // Minimizing.
-            // We start out with the current state of the board, as if we were to pass, and we want to find a move that improves that.
-            let mut best_score: f64 = score(board);
-
-            // <BEGIN HACK>, for white exclude points in black's territory. Unless they capture an enemy group.
-            let mut legal_moves: BitSet = get_legal_moves(board, board_history);
-            let mut capture_moves: BitSet = BitSet::new();
-            for legal_move in legal_moves {
-                if captures_enemy_group(legal_move, board) {
-                    capture_moves.insert(legal_move);
-                }
+            if minimax_score < best_score {
+                best_score = minimax_score;
+                best_move = Some(point);
             }
-            let points_not_in_black_territory = !get_points_in_territory(board, Player::Black);
-            legal_moves &= points_not_in_black_territory;
-            legal_moves |= capture_moves;
-            // <END HACK>
-
-            for point in legal_moves {
-                let minimax_score: f64 = minimax_alphabeta(&make_move(point, board), &deeper_history, depth - 1, alpha, beta);
-                // Minimizing.
-                best_score = best_score.min(minimax_score);
-                beta = beta.min(best_score);
-                if beta <= alpha {
-                    break;
-                }
+            beta = beta.min(best_score);
+        }
+        if beta <= alpha {
+            if !captures_enemy_group(point, board) {
+                tables.add_killer(ply, point);
             }
-            return best_score;
+            break;
         }
     }
+
+    // Values outside the window are only bounds, the moves that could have made them better or worse were cut off.
+    let bound: Bound = if best_score <= window_alpha {
+        Bound::Upper
+    } else if best_score >= window_beta {
+        Bound::Lower
+    } else {
+        Bound::Exact
+    };
+    tables.store(
+        key,
+        TableEntry {
+            depth: depth,
+            value: best_score,
+            bound: bound,
+            best_move: best_move,
+        },
+    );
+    return Some(best_score);
+}
+
+/// The candidate moves in the order to search them, the ones most likely to cause a cutoff first.
+/// That's the best move from an earlier search of the position, then captures, then killer moves, then the rest.
+fn ordered_moves(
+    board: &Board,
+    board_history: &BoardHistory,
+    settled: &SettledPoints,
+    best_move: Option<usize>,
+    killers: [Option<usize>; KILLER_SLOTS],
+) -> Vec<usize> {
+    let mut moves: Vec<(u8, usize)> = candidate_moves(board, board_history, settled)
+        .map(|point| {
+            let priority: u8 = if Some(point) == best_move {
+                0
+            } else if captures_enemy_group(point, board) {
+                1
+            } else if killers.contains(&Some(point)) {
+                2
+            } else {
+                3
+            };
+            return (priority, point);
+        })
+        .collect();
+    moves.sort_by_key(|(priority, _)| *priority);
+    return moves.into_iter().map(|(_, point)| point).collect();
 }
 
-fn get_points_in_territory(board: &Board, player: Player) -> BitSet {
-    let mut territory: BitSet = BitSet::new();
+/// The legal moves worth searching for the player to move.
+/// Moves on points that are settled already, going by Benson's algorithm, are left out unless they capture.
+/// The settled points can be an earlier board's, the points stay settled for the rest of the game.
+fn candidate_moves(board: &Board, board_history: &BoardHistory, settled: &SettledPoints) -> BitSet {
+    let pointless: BitSet = settled.for_player(board.player);
 
-    let mut seen_stones: SeenStones;
-    match player {
-        Player::Black => seen_stones = SeenStones::Black,
-        Player::White => seen_stones = SeenStones::White,
+    let mut candidate_moves: BitSet = get_legal_moves(board, board_history);
+    for point in candidate_moves & pointless {
+        if !captures_enemy_group(point, board) {
+            candidate_moves.remove(point);
+        }
     }
+    return candidate_moves;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board_display::Heatmap;
+    use crate::board_from_string::board_from_string;
+    use crate::evaluator::FinalScore;
+    use rand::SeedableRng;
+    use rustc_hash::FxBuildHasher;
+    use std::collections::HashSet;
+
+    #[test]
+    fn both_players_take_the_capture() {
+        // Whoever plays on the right of the white stones captures, and wins the game.
+        let black_board: Box<[u8]> = board_from_string(
+            "
+    XXXXX
+    X.X.X
+    XXXXX
+    OOOO.
+    XXXXX
+    ",
+            5,
+        );
+        // The same position with the colors the other way around.
+        let white_board: Box<[u8]> = board_from_string(
+            "
+    OOOOO
+    O.O.O
+    OOOOO
+    XXXX.
+    OOOOO
+    ",
+            5,
+        );
+        let board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+        let mut rng: RNG = RNG::seed_from_u64(15);
 
-    for point in 0..board.board.len() {
-        if board.board[point] == PointState::Empty as u8 && !territory.contains(point) {
-            let mut group: BitSet = BitSet::new();
-            score_group_territory(point, board, &mut group, &mut seen_stones);
-            territory |= group;
+        for (board, player, komi) in [(black_board, Player::Black, 0.5), (white_board, Player::White, -0.5)] {
+            let board: Board = Board::new(board, 5, player, komi, false);
+            let analysis: Analysis = minimax_ab_strategy(&board, &board_history, false, &FinalScore, Deadline::after(0.0), &mut rng);
+            assert_eq!(
+                analysis.best_move(),
+                Move::from_index(9, 25),
+                "{:?} should capture, the analysis is\n{}",
+                player,
+                Heatmap::new(&board, &analysis.scores)
+            );
         }
     }
 
-    return territory;
+    #[test]
+    fn both_players_pass_back_when_ahead() {
+        let black_board: Box<[u8]> = board_from_string(
+            "
+    .X.X.
+    XXXXX
+    XXXXX
+    OOOOO
+    .O.O.
+    ",
+            5,
+        );
+        let white_board: Box<[u8]> = board_from_string(
+            "
+    .O.O.
+    OOOOO
+    OOOOO
+    XXXXX
+    .X.X.
+    ",
+            5,
+        );
+        let board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+        let mut rng: RNG = RNG::seed_from_u64(16);
+
+        for (board, player) in [(black_board, Player::Black), (white_board, Player::White)] {
+            let board: Board = Board::new(board, 5, player, 0.5, true);
+            let analysis: Analysis = minimax_ab_strategy(&board, &board_history, true, &FinalScore, Deadline::after(10.0), &mut rng);
+            assert_eq!(
+                analysis.best_move(),
+                Move::Pass,
+                "{:?} should pass, the analysis is\n{}",
+                player,
+                Heatmap::new(&board, &analysis.scores)
+            );
+        }
+    }
+
+    #[test]
+    fn the_tables_dont_change_the_result() {
+        // Nothing can be captured within a few moves, so a position can only be reached at one depth,
+        // and what's in the transposition table is exactly what searching again would find.
+        let board: Board = Board::new(
+            board_from_string(
+                "
+    .X.O.
+    .X.O.
+    .X.O.
+    .X.O.
+    .X.O.
+    ",
+                5,
+            ),
+            5,
+            Player::Black,
+            0.5,
+            false,
+        );
+        let board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+        let mut rng: RNG = RNG::seed_from_u64(18);
+        let settled: SettledPoints = settled_points(&board);
+
+        let mut tables: SearchTables = SearchTables::new();
+        for depth in 1..=3 {
+            let deepened: Analysis = minimax_at_depth(
+                &board,
+                &board_history,
+                false,
+                depth,
+                &settled,
+                &mut tables,
+                &FinalScore,
+                &Deadline::never(),
+                &mut rng,
+            )
+            .unwrap();
+            let fresh: Analysis = minimax_at_depth(
+                &board,
+                &board_history,
+                false,
+                depth,
+                &settled,
+                &mut SearchTables::new(),
+                &FinalScore,
+                &Deadline::never(),
+                &mut rng,
+            )
+            .unwrap();
+            assert_eq!(deepened, fresh, "At depth {}", depth);
+        }
+        assert!(tables.transpositions.len() > 0);
+        assert!(tables.transpositions.values().any(|entry| entry.bound != Bound::Exact));
+    }
+
+    #[test]
+    fn moves_are_ordered_best_first() {
+        // White can capture at 3, or not.
+        let board: Board = Board::new(
+            board_from_string(
+                "
+    .....
+    .....
+    .....
+    OOO..
+    XXX.O
+    ",
+                5,
+            ),
+            5,
+            Player::White,
+            0.5,
+            false,
+        );
+        let board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+
+        let moves: Vec<usize> = ordered_moves(&board, &board_history, &settled_points(&board), None, [None; KILLER_SLOTS]);
+        assert_eq!(moves[0], 3);
+        assert!(moves[1..].windows(2).all(|pair| pair[0] < pair[1]));
+
+        let moves: Vec<usize> = ordered_moves(&board, &board_history, &settled_points(&board), Some(20), [Some(15), Some(24)]);
+        assert_eq!(moves[..4], [20, 3, 15, 24]);
+        assert_eq!(moves.len(), 18);
+
+        let mut tables: SearchTables = SearchTables::new();
+        tables.add_killer(3, 15);
+        tables.add_killer(3, 24);
+        tables.add_killer(3, 24);
+        assert_eq!(tables.killers(3), [Some(24), Some(15)]);
+        assert_eq!(tables.killers(4), [None, None]);
+    }
+
+    #[test]
+    fn safe_regions_are_not_searched() {
+        // Black is alive with plenty of eyes, and there's a dead white stone in one of them.
+        let board_string: &str = "
+    .X.X.
+    XXXXX
+    XXOXX
+    XX.XX
+    .X.X.
+    ";
+        let board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+
+        // Black only needs to capture the dead stone.
+        let black: Board = Board::new(board_from_string(board_string, 5), 5, Player::Black, 5.5, false);
+        assert_eq!(
+            candidate_moves(&black, &board_history, &settled_points(&black)).collect::<Vec<usize>>(),
+            vec![2, 7]
+        );
+
+        // White has nowhere to go.
+        let white: Board = Board::new(board_from_string(board_string, 5), 5, Player::White, 5.5, false);
+        assert!(!get_legal_moves(&white, &board_history).empty());
+        assert!(candidate_moves(&white, &board_history, &settled_points(&white)).empty());
+    }
 }
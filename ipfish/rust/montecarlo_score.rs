@@ -1,13 +1,11 @@
 use crate::RNG;
+use crate::benson::SettledPoints;
 use crate::bitset::BitSet;
-use crate::board::Board;
 use crate::board::BoardHistory;
-use crate::final_score::final_score;
-use crate::get_adjacent_points::get_adjacent_points;
-use crate::get_legal_moves::get_legal_moves;
-use crate::make_move::make_move;
-use crate::pass_move::pass_move;
-use crate::point_state::PointState;
+use crate::ownership::Ownership;
+use crate::player::Player;
+use crate::playout_board::PlayoutBoard;
+use crate::score_stats::ScoreStats;
 
 use rand::seq::IndexedRandom;
 
@@ -18,71 +16,139 @@ pub enum Winner {
     BlackWin = 1,
 }
 
-/// Returns the percentage (f64 between 0 and 1) of monte carlo simulations that resulted in a black win.
+impl Winner {
+    /// The winner of a game with the final score. Positive value = win for black.
+    pub fn from_score(score: f64) -> Winner {
+        if score > 0.0 {
+            return Winner::BlackWin;
+        } else {
+            return Winner::WhiteWin;
+        }
+    }
+}
+
+/// The points each player played on during a simulation. Used for all moves as first statistics.
+#[derive(Clone, Copy, Debug)]
+pub struct PlayedPoints {
+    pub black: BitSet,
+    pub white: BitSet,
+}
+
+impl PlayedPoints {
+    pub fn new() -> PlayedPoints {
+        return PlayedPoints {
+            black: BitSet::new(),
+            white: BitSet::new(),
+        };
+    }
+    pub fn insert(&mut self, player: Player, point: usize) {
+        match player {
+            Player::Black => self.black.insert(point),
+            Player::White => self.white.insert(point),
+        }
+    }
+    pub fn contains(&self, player: Player, point: usize) -> bool {
+        return match player {
+            Player::Black => self.black.contains(point),
+            Player::White => self.white.contains(point),
+        };
+    }
+}
+
+/// What a batch of monte carlo simulations from the same board came to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MontecarloResult {
+    /// The number of simulations that resulted in a black win.
+    pub black_wins: u32,
+    /// Who owned every point at the end of the simulations.
+    pub ownership: Ownership,
+    /// The final score margins.
+    pub scores: ScoreStats,
+}
+
+/// Runs monte carlo simulations from the board, and returns how many black won, who owned the points and the final scores.
 /// Doesn't play moves that would be self sacrifice for the oppoent to play during simulation.
 /// More simulations costs more compute time.
+/// Works on any board representation, a BitBoard is the fastest one for this.
 ///
 /// # Arguments
 ///
 /// * `board` - The board state to evaluate.
 /// * `simulation_count` - The number of montecarlo simulations to run.
-pub fn montecarlo_score(board: &Board, board_history: &BoardHistory, simulation_count: u32, rng: &mut RNG) -> u32 {
-    let mut black_wins: u32 = 0;
+pub fn montecarlo_score<B: PlayoutBoard>(board: &B, board_history: &BoardHistory, simulation_count: u32, rng: &mut RNG) -> MontecarloResult {
+    let mut result: MontecarloResult = MontecarloResult {
+        black_wins: 0,
+        ownership: Ownership::new(board.point_count()),
+        scores: ScoreStats::new(),
+    };
+    let settled: SettledPoints = board.settled_points();
 
     for _ in 0..simulation_count {
-        black_wins += montecarlo_simulation(board.clone(), board_history.clone(), rng) as u32;
+        let final_board: B = montecarlo_playout(board.clone(), board_history.clone(), &settled, &mut PlayedPoints::new(), rng);
+        let score: f64 = final_board.score();
+        result.ownership.record(&final_board);
+        result.scores.add(score, 1.0);
+        result.black_wins += Winner::from_score(score) as u32;
     }
-    return black_wins;
+    return result;
+}
+
+pub fn montecarlo_simulation<B: PlayoutBoard>(board: B, board_history: BoardHistory, rng: &mut RNG) -> Winner {
+    return montecarlo_simulation_recorded(board, board_history, &mut PlayedPoints::new(), rng);
 }
 
-pub fn montecarlo_simulation(mut board: Board, mut board_history: BoardHistory, rng: &mut RNG) -> Winner {
-    for _ in 0..board.board.len() {
-        match play_random_move(&board, &mut board_history, rng) {
+/// Same as montecarlo_simulation, but also records the points each player played on in `played`.
+/// Passes aren't recorded.
+pub fn montecarlo_simulation_recorded<B: PlayoutBoard>(board: B, board_history: BoardHistory, played: &mut PlayedPoints, rng: &mut RNG) -> Winner {
+    let settled: SettledPoints = board.settled_points();
+    return Winner::from_score(montecarlo_playout(board, board_history, &settled, played, rng).score());
+}
+
+/// Plays a random game from the board until both players pass, or it has gone on for as many moves as the board has points.
+/// Returns the board the game ended with, and records the points each player played on in `played`.
+/// `settled` are the board's settled_points. Playing where they still may, neither player can unsettle them,
+/// so they're found once and hold for the whole game, and for every other game played out from the same board.
+pub fn montecarlo_playout<B: PlayoutBoard>(
+    mut board: B,
+    mut board_history: BoardHistory,
+    settled: &SettledPoints,
+    played: &mut PlayedPoints,
+    rng: &mut RNG,
+) -> B {
+    for _ in 0..board.point_count() {
+        match play_random_move(&board, &mut board_history, settled, played, rng) {
             Some(s) => {
                 board = s;
             }
             None => break,
         }
     }
-    let result: f64 = final_score(&board);
-
-    if result > 0.0 {
-        return Winner::BlackWin;
-    } else {
-        return Winner::WhiteWin;
-    }
+    return board;
 }
 
-fn play_random_move(board: &Board, board_history: &mut BoardHistory, rng: &mut RNG) -> Option<Board> {
-    let mut possible_moves: Vec<usize> = Vec::new();
-    let legal_for_opponent = get_legal_moves(&pass_move(board), board_history);
-    for legal_move in get_legal_moves(&board, board_history) {
-        // Only play in spaces surrounded by friendlies/walls if they're legal for the opponent.
-        let mut true_eye: bool = true;
-        for adjacent_point in get_adjacent_points(legal_move, &board) {
-            // It's not a true eye, if there's an adjacent empty square or enemy piece
-            if board.board[adjacent_point] == !board.player as u8 || board.board[adjacent_point] == PointState::Empty as u8 {
-                true_eye = false;
-                break;
-            }
-        }
-        if !true_eye || legal_for_opponent.contains(legal_move) {
-            possible_moves.push(legal_move);
-        }
-    }
+fn play_random_move<B: PlayoutBoard>(
+    board: &B,
+    board_history: &mut BoardHistory,
+    settled: &SettledPoints,
+    played: &mut PlayedPoints,
+    rng: &mut RNG,
+) -> Option<B> {
+    // Only play in spaces surrounded by friendlies/walls if they're legal for the opponent, and never in settled territory.
+    let possible_moves: Vec<usize> = (board.playout_moves(board_history) & !settled.for_player(board.player())).collect();
 
     let chosen_move: Option<&usize> = possible_moves.choose(rng);
     match chosen_move {
         None => {
-            if !board.opponent_passed {
-                return Some(pass_move(board));
+            if !board.opponent_passed() {
+                return Some(board.pass());
             } else {
                 return None;
             }
         }
         Some(s) => {
-            let new_board: Board = make_move(*s, board);
-            board_history.insert(new_board.board.clone());
+            played.insert(board.player(), *s);
+            let new_board: B = board.play(*s);
+            board_history.insert(new_board.hash());
             return Some(new_board);
         }
     }
@@ -92,8 +158,12 @@ fn play_random_move(board: &Board, board_history: &mut BoardHistory, rng: &mut R
 mod tests {
 
     use super::*;
+    use crate::bitboard::BitBoard;
+    use crate::board::Board;
     use crate::board_from_string::board_from_string;
+    use crate::final_score::final_score;
     use crate::player::Player;
+    use crate::point_state::PointState;
     use rand::prelude::*;
     use rustc_hash::FxBuildHasher;
     use std::collections::HashSet;
@@ -106,6 +176,7 @@ mod tests {
     XX.X#
     XXXX.
     .X..X
+    #####
     ",
             5,
         );
@@ -113,21 +184,86 @@ mod tests {
         let board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher::default());
         let mut rng: RNG = RNG::from_rng(&mut rand::rng());
 
-        let board: Board = Board {
-            board: board.clone(),
-            size: 5,
-            player: Player::Black,
-            komi: 7.5,
-            opponent_passed: false,
-        };
+        let board: Board = Board::new(board.clone(), 5, Player::Black, 7.5, false);
 
         let score = final_score(&board);
         println!("Final score = {}", score);
         assert!(score > 0.0);
 
+        let winner = montecarlo_simulation(BitBoard::from(&board), board_history.clone(), &mut rng);
+        assert_eq!(winner, Winner::BlackWin, "Black literally can not lose with the bitboard either...");
+
         let winner = montecarlo_simulation(board, board_history, &mut rng);
 
         assert_eq!(winner, Winner::BlackWin, "Black literally can not lose...")
         // Black literally can not lose this position.
     }
+
+    #[test]
+    fn backends_play_identical_simulations() {
+        let board: Board = Board::new(vec![PointState::Empty as u8; 49].into_boxed_slice(), 7, Player::Black, 5.5, false);
+        let board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+
+        // Both backends generate the same legal moves in the same order, so they consume the rng identically.
+        let board_result: MontecarloResult = montecarlo_score(&board, &board_history, 50, &mut RNG::seed_from_u64(3));
+        let bitboard_result: MontecarloResult = montecarlo_score(&BitBoard::from(&board), &board_history, 50, &mut RNG::seed_from_u64(3));
+        assert_eq!(board_result, bitboard_result);
+        assert_eq!(board_result.ownership.playouts, 50);
+        assert_eq!(board_result.scores.weight, 50.0);
+        // Random games on an empty board go every which way.
+        assert!(board_result.scores.variance() > 0.0);
+    }
+
+    #[test]
+    fn simulations_record_the_points_played() {
+        let board: Board = Board::new(vec![PointState::Empty as u8; 25].into_boxed_slice(), 5, Player::Black, 5.5, false);
+        let board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+
+        let mut played: PlayedPoints = PlayedPoints::new();
+        let winner: Winner = montecarlo_simulation_recorded(board.clone(), board_history.clone(), &mut played, &mut RNG::seed_from_u64(4));
+        let mut bitboard_played: PlayedPoints = PlayedPoints::new();
+        let bitboard_winner: Winner =
+            montecarlo_simulation_recorded(BitBoard::from(&board), board_history.clone(), &mut bitboard_played, &mut RNG::seed_from_u64(4));
+
+        assert_eq!(winner, bitboard_winner);
+        assert_eq!(played.black, bitboard_played.black);
+        assert_eq!(played.white, bitboard_played.white);
+        // A random game on an empty board fills most of it, with both colors.
+        assert!(played.black.len() >= 10);
+        assert!(played.white.len() >= 10);
+        assert_eq!(played.black.contains(25), false);
+
+        // Recording doesn't change the simulation.
+        assert_eq!(montecarlo_simulation(board, board_history, &mut RNG::seed_from_u64(4)), winner);
+    }
+
+    #[test]
+    fn simulations_stay_out_of_settled_territory() {
+        let board: Board = Board::new(
+            board_from_string(
+                "
+    .X..X
+    XXXXX
+    .....
+    .....
+    .....
+    ",
+                5,
+            ),
+            5,
+            Player::White,
+            5.5,
+            false,
+        );
+        let board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+
+        let mut played: PlayedPoints = PlayedPoints::new();
+        montecarlo_simulation_recorded(BitBoard::from(&board), board_history, &mut played, &mut RNG::seed_from_u64(5));
+        // Black's eyes are black's whatever happens, so neither player plays in them, not even in the one that isn't a true eye.
+        for eye in [20, 22, 23] {
+            assert!(!played.contains(Player::Black, eye));
+            assert!(!played.contains(Player::White, eye));
+        }
+        assert!(played.white.len() > 0);
+    }
 }
@@ -0,0 +1,124 @@
+use crate::RNG;
+use crate::board::BoardHistory;
+use crate::montecarlo_score::montecarlo_score;
+use crate::player::Player;
+use crate::playout_board::PlayoutBoard;
+
+/// How often every point ended up black's, white's or neither's in a batch of playouts, by the same rules as final_score.
+/// Shows where the game is still undecided, and what the final board is expected to look like.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ownership {
+    /// For every point, the number of playouts it counted for black in.
+    pub black: Vec<u32>,
+    /// For every point, the number of playouts it counted for white in.
+    pub white: Vec<u32>,
+    /// The number of playouts recorded.
+    pub playouts: u32,
+}
+
+impl Ownership {
+    pub fn new(point_count: usize) -> Ownership {
+        return Ownership {
+            black: vec![0; point_count],
+            white: vec![0; point_count],
+            playouts: 0,
+        };
+    }
+
+    /// Adds the board a playout ended with.
+    pub fn record<B: PlayoutBoard>(&mut self, board: &B) {
+        for point in board.area(Player::Black) {
+            self.black[point] += 1;
+        }
+        for point in board.area(Player::White) {
+            self.white[point] += 1;
+        }
+        self.playouts += 1;
+    }
+
+    /// Adds the playouts recorded in another ownership of the same board.
+    pub fn add(&mut self, other: &Ownership) {
+        for point in 0..self.black.len() {
+            self.black[point] += other.black[point];
+            self.white[point] += other.white[point];
+        }
+        self.playouts += other.playouts;
+    }
+
+    /// The number of playouts the point counted for neither player in.
+    pub fn neutral(&self, point: usize) -> u32 {
+        return self.playouts - self.black[point] - self.white[point];
+    }
+
+    /// For every point, the share of the playouts it counted for black in minus the share it counted for white in.
+    /// From -1 for sure white's, through 0 for undecided or neutral, to 1 for sure black's.
+    pub fn map(&self) -> Vec<f64> {
+        return (0..self.black.len())
+            .map(|point| self.share(Player::Black, point) - self.share(Player::White, point))
+            .collect();
+    }
+
+    /// The share of the playouts the point counted for the player in. Without any playouts there's no telling, so 0.5.
+    pub fn share(&self, player: Player, point: usize) -> f64 {
+        if self.playouts == 0 {
+            return 0.5;
+        }
+        let owned: u32 = match player {
+            Player::Black => self.black[point],
+            Player::White => self.white[point],
+        };
+        return owned as f64 / self.playouts as f64;
+    }
+}
+
+/// Plays out random games from the board, and returns who owned every point at the end of them.
+///
+/// # Arguments
+///
+/// * `board` - The board state to play out from.
+/// * `board_history` - The board history used for superko, including the board itself.
+/// * `simulation_count` - The number of playouts.
+pub fn montecarlo_ownership<B: PlayoutBoard>(board: &B, board_history: &BoardHistory, simulation_count: u32, rng: &mut RNG) -> Ownership {
+    return montecarlo_score(board, board_history, simulation_count, rng).ownership;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitboard::BitBoard;
+    use crate::board::Board;
+    use crate::board_from_string::board_from_string;
+    use rand::SeedableRng;
+    use rustc_hash::FxBuildHasher;
+    use std::collections::HashSet;
+
+    #[test]
+    fn settled_boards_are_owned_for_sure() {
+        let board: Board = Board::new(
+            board_from_string(
+                "
+    .X.X.
+    XXXXX
+    XXXXX
+    OOOOO
+    .O#O.
+    ",
+                5,
+            ),
+            5,
+            Player::Black,
+            0.5,
+            false,
+        );
+        let board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+        let ownership: Ownership = montecarlo_ownership(&BitBoard::from(&board), &board_history, 10, &mut RNG::seed_from_u64(18));
+
+        assert_eq!(ownership.playouts, 10);
+        assert_eq!(ownership.share(Player::Black, 20), 1.0);
+        assert_eq!(ownership.share(Player::White, 0), 1.0);
+        assert_eq!(ownership.share(Player::Black, 0), 0.0);
+        // Offline points are nobody's.
+        assert_eq!(ownership.neutral(2), 10);
+        assert_eq!(ownership.map()[..5], [-1.0, -1.0, 0.0, -1.0, -1.0]);
+    }
+}
@@ -7,5 +7,8 @@ pub fn pass_move(board: &Board) -> Board {
         player: !board.player,
         komi: board.komi,
         opponent_passed: true,
+        hash: board.hash,
+        chain_ids: board.chain_ids.clone(),
+        chains: board.chains.clone(),
     };
 }
@@ -1,40 +1,212 @@
 use crate::RNG;
+use crate::bitboard::BitBoard;
 use crate::board::{Board, BoardHistory};
+use crate::clock::Deadline;
+use crate::engine_config::{EngineConfig, StrategyChoice};
+use crate::evaluator::{Evaluator, FinalScore};
 use crate::get_legal_moves::get_legal_moves_strict;
-use crate::mcts_strategy::{MCTree, Node, mcts_strategy};
-use crate::montecarlo_score::montecarlo_score;
-use crate::minimax_ab_strategy::minimax_ab_strategy;
+use crate::mcts_strategy::{MCTree, MctsStrategy};
+use crate::minimax_ab_strategy::MinimaxAbStrategy;
+use crate::montecarlo_score::{MontecarloResult, montecarlo_score};
+use crate::ownership::Ownership;
+use crate::point::{Move, Point};
+use crate::strategy::{Analysis, PassStrategy, Strategy};
 
-/// Heuristically pick a strategy based on the in game situation and give the evaluation of that strategy.
+// The guesstimate plays its simulations in batches of this many.
+const GUESSTIMATE_BATCH_SIZE: u32 = 10;
+
+// How many points of score lead make a won move about 73% of the way to the top score, when maximizing the score.
+const SCORE_LEAD_SCALE: f64 = 10.0;
+
+/// Pick a strategy based on the configuration and the in game situation, and give the evaluation of that strategy.
 /// Do some other heuristics too.
-pub fn pick_strategy(board: Board, board_history: BoardHistory, opponent_passed: bool, rng: &mut RNG) -> Vec<f64> {
-    let mut result: Vec<f64> = vec![f64::NEG_INFINITY; board.board.len() + 1];
-    let legal_moves: crate::bitset::BitSet = get_legal_moves_strict(&board, &board_history);
-    let pass_result: usize = result.len() - 1;
+/// The strategies search until the deadline, and return the best result they had by then.
+/// If a search tree rooted at the board is given, Monte Carlo Tree Search carries on from it rather than starting over.
+/// Monte Carlo Tree Search uses the given evaluator, the minimax finisher always goes by the final score.
+/// Strategies that don't play any games out get the ownership of the guesstimate's games.
+pub fn pick_strategy(
+    board: &Board,
+    board_history: &BoardHistory,
+    config: &EngineConfig,
+    evaluator: &dyn Evaluator,
+    tree: Option<&mut MCTree>,
+    deadline: Deadline,
+    rng: &mut RNG,
+) -> Analysis {
+    let legal_moves: crate::bitset::BitSet = get_legal_moves_strict(board, board_history);
 
     // Play tengen first move on boards that only have 1 or two offline nodes.
-    if board_history.len() <= 1 {
-        let tengen = board.board.len() / 2;
-        if legal_moves.len() > 21 && legal_moves.contains(tengen) {
-            let mut result: Vec<f64> = vec![f64::NEG_INFINITY; board.board.len() + 1];
-            result[tengen] = f64::INFINITY;
-            return result;
+    if config.tengen_opening && board_history.len() <= 1 {
+        let tengen: Point = Point::from_xy(board.size / 2, board.size / 2, board.size);
+        if legal_moves.len() > 21 && legal_moves.contains(tengen.index()) {
+            return Analysis::only(board, Move::Play(tengen), f64::INFINITY);
         }
     }
 
-    let guesstimate: u32 = montecarlo_score(&board, &board_history, 100, rng);
+    let mut ownership: Option<Ownership> = None;
+    let mut strategy: Box<dyn Strategy + '_> = match config.strategy {
+        StrategyChoice::MonteCarloTreeSearch => Box::new(MctsStrategy {
+            evaluator: evaluator,
+            tree: tree,
+            rave_schedule: config.rave_schedule(),
+        }),
+        StrategyChoice::Minimax => Box::new(MinimaxAbStrategy { evaluator: &FinalScore }),
+        StrategyChoice::Auto => {
+            let (guesstimate, guesstimate_ownership): (f64, Ownership) = guesstimate(board, board_history, config, deadline, rng);
+            ownership = Some(guesstimate_ownership).filter(|ownership| ownership.playouts > 0);
+            // Winning position!
+            if guesstimate > config.minimax_threshold {
+                Box::new(MinimaxAbStrategy { evaluator: &FinalScore })
+            }
+            // Lossing position, just pass...
+            else if guesstimate < config.pass_threshold {
+                Box::new(PassStrategy)
+            }
+            // Monte Carlo Tree Search!
+            else {
+                Box::new(MctsStrategy {
+                    evaluator: evaluator,
+                    tree: tree,
+                    rave_schedule: config.rave_schedule(),
+                })
+            }
+        }
+    };
+    let mut analysis: Analysis = strategy.analyze(board, board_history, deadline, rng);
+    if analysis.ownership.is_none() {
+        analysis.ownership = ownership;
+    }
+    if config.maximize_score {
+        prefer_score_lead(&mut analysis, config.minimax_threshold);
+    }
+    return analysis;
+}
 
-    // Winning position!
-    if guesstimate > 95 {
-        return minimax_ab_strategy(&board, &board_history, opponent_passed);
+/// Once the win rate of a move is close to certain, it can't tell won moves apart any more, and they all look equally good.
+/// Rescores the moves with a win rate above the threshold by their score lead instead, so we keep winning by as much as we can.
+/// They still score higher than the moves below the threshold. Only Monte Carlo Tree Search has score leads,
+/// its scores are win ratios minus 0.5. Both are for the opponent's best reply, so they measure the same games.
+fn prefer_score_lead(analysis: &mut Analysis, threshold: f64) {
+    let saturated: f64 = threshold - 0.5;
+    for played in analysis.moves() {
+        let lead: f64 = analysis.score_lead(played);
+        if analysis.score(played) > saturated && !lead.is_nan() {
+            let logistic: f64 = 1.0 / (1.0 + (-lead / SCORE_LEAD_SCALE).exp());
+            analysis.set_score(played, saturated + (0.5 - saturated) * logistic);
+        }
     }
-    // Lossing position, just pass...
-    else if guesstimate < 5 {
-        result[pass_result] = 0.0;
-        return result;
+}
+
+/// Spends a little of the time on a guesstimate of who's winning, the share of random games won by the player to move.
+/// Also returns the ownership of the games.
+/// The simulations are played in batches, so the counts in the config are rounded up to whole batches.
+fn guesstimate(board: &Board, board_history: &BoardHistory, config: &EngineConfig, deadline: Deadline, rng: &mut RNG) -> (f64, Ownership) {
+    let guesstimate_deadline: Deadline = deadline.fraction(config.guesstimate_share);
+    let bitboard: BitBoard = BitBoard::from(board);
+    let mut ownership: Ownership = Ownership::new(board.board.len());
+    let mut simulations: u32 = 0;
+    let mut black_wins: u32 = 0;
+    while simulations < config.guesstimate_min_simulations || (simulations < config.guesstimate_max_simulations && !guesstimate_deadline.expired()) {
+        let batch: MontecarloResult = montecarlo_score(&bitboard, board_history, GUESSTIMATE_BATCH_SIZE, rng);
+        black_wins += batch.black_wins;
+        ownership.add(&batch.ownership);
+        simulations += GUESSTIMATE_BATCH_SIZE;
     }
-    // Monte Carlo Tree Search!
-    else {
-        return mcts_strategy(board, board_history, rng);
+    // Without any simulations there's no telling.
+    if simulations == 0 {
+        return (0.5, ownership);
+    }
+    return (board.player.value_for(black_wins as f64 / simulations as f64), ownership);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board_from_string::board_from_string;
+    use crate::evaluator::RandomPlayouts;
+    use crate::player::Player;
+    use crate::point_state::PointState;
+    use rand::SeedableRng;
+    use rustc_hash::FxBuildHasher;
+    use std::collections::HashSet;
+
+    #[test]
+    fn the_config_decides_the_strategy() {
+        let board: Board = Board::new(vec![PointState::Empty as u8; 25].into_boxed_slice(), 5, Player::Black, 5.5, false);
+        let mut board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+        board_history.insert(board.hash);
+        let evaluator: RandomPlayouts = RandomPlayouts { simulation_count: 5 };
+        let mut rng: RNG = RNG::seed_from_u64(13);
+
+        let tengen: Analysis = pick_strategy(
+            &board,
+            &board_history,
+            &EngineConfig::default(),
+            &evaluator,
+            None,
+            Deadline::after(0.0),
+            &mut rng,
+        );
+        assert_eq!(tengen, Analysis::only(&board, Move::Play(Point::from_label("c3", 5).unwrap()), f64::INFINITY));
+
+        // Thresholds nothing gets past make us give up.
+        let config: EngineConfig = EngineConfig {
+            tengen_opening: false,
+            minimax_threshold: 2.0,
+            pass_threshold: 2.0,
+            ..EngineConfig::default()
+        };
+        let pass: Analysis = pick_strategy(&board, &board_history, &config, &evaluator, None, Deadline::after(0.0), &mut rng);
+        assert_eq!(pass.best_move(), Move::Pass);
+        assert_eq!(pass.scores, Analysis::only(&board, Move::Pass, 0.0).scores);
+        // Passing doesn't play any games out, the guesstimate did.
+        assert_eq!(pass.ownership.expect("The guesstimate's ownership").playouts, 10);
+
+        // Unless the strategy is forced.
+        let config: EngineConfig = EngineConfig {
+            strategy: StrategyChoice::MonteCarloTreeSearch,
+            ..config
+        };
+        let mcts: Analysis = pick_strategy(&board, &board_history, &config, &evaluator, None, Deadline::after(0.0), &mut rng);
+        assert!(mcts.scores.iter().all(|score| score.is_finite()));
+        assert!(mcts.ownership.is_some());
+    }
+
+    #[test]
+    fn won_moves_are_picked_by_score_lead() {
+        let mut analysis: Analysis = Analysis {
+            scores: vec![0.47, 0.49, 0.1, f64::NEG_INFINITY],
+            ownership: None,
+            score_leads: Some(vec![20.0, 5.0, 30.0, f64::NAN]),
+        };
+        prefer_score_lead(&mut analysis, 0.95);
+        // Both won moves are sure wins, the first one wins by more.
+        assert!(analysis.scores[0] > analysis.scores[1]);
+        assert!(analysis.scores[1] > 0.45);
+        // Winning by a lot in the games it wins doesn't make up for not winning often.
+        assert_eq!(analysis.scores[2], 0.1);
+        assert_eq!(analysis.scores[3], f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn the_guesstimate_is_for_the_player_to_move() {
+        // White is ahead, and the board is settled.
+        let board: Box<[u8]> = board_from_string(
+            "
+    .O.O.
+    OOOOO
+    OOOOO
+    XXXXX
+    .X.X.
+    ",
+            5,
+        );
+        let board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+        let mut rng: RNG = RNG::seed_from_u64(17);
+
+        let white: Board = Board::new(board.clone(), 5, Player::White, 0.5, false);
+        assert!(guesstimate(&white, &board_history, &EngineConfig::default(), Deadline::after(0.0), &mut rng).0 > 0.95);
+        let black: Board = Board::new(board, 5, Player::Black, 0.5, false);
+        assert!(guesstimate(&black, &board_history, &EngineConfig::default(), Deadline::after(0.0), &mut rng).0 < 0.05);
     }
 }
@@ -17,6 +17,25 @@ impl Not for Player {
     }
 }
 
+impl Player {
+    /// Turns a value between 0 and 1 from black's point of view, like an evaluation, into the player's point of view.
+    pub fn value_for(self, black_value: f64) -> f64 {
+        return match self {
+            Player::Black => black_value,
+            Player::White => 1.0 - black_value,
+        };
+    }
+
+    /// Turns a score from black's point of view, like the final score, into the player's point of view.
+    /// Positive value = win for the player.
+    pub fn score_for(self, black_score: f64) -> f64 {
+        return match self {
+            Player::Black => black_score,
+            Player::White => -black_score,
+        };
+    }
+}
+
 impl From<f64> for Player {
     fn from(n: f64) -> Self {
         if n as u8 == Player::Black as u8 {
@@ -0,0 +1,100 @@
+use crate::benson::{SettledPoints, settled_points};
+use crate::bitboard::BitBoard;
+use crate::bitset::BitSet;
+use crate::board::{Board, BoardHistory};
+use crate::final_score::final_score;
+use crate::get_adjacent_points::get_adjacent_points;
+use crate::get_legal_moves::get_legal_moves;
+use crate::make_move::make_move;
+use crate::pass_move::pass_move;
+use crate::player::Player;
+use crate::point::Point;
+use crate::point_state::PointState;
+
+/// The operations a monte carlo playout needs from a board representation.
+/// Implemented by the regular Board, and by the BitBoard which is built for playout speed.
+pub trait PlayoutBoard: Clone {
+    /// Who's turn it currently is to play.
+    fn player(&self) -> Player;
+    /// True if the opponent passed last turn, false otherwise.
+    fn opponent_passed(&self) -> bool;
+    /// The number of points on the board, including offline ones.
+    fn point_count(&self) -> usize;
+    /// The zobrist hash of the pieces on the board, as stored in the board history.
+    fn hash(&self) -> u64;
+    /// Returns a bitset where the 1 bits are legal moves, same as get_legal_moves.
+    fn legal_moves(&self, board_history: &BoardHistory) -> BitSet;
+    /// Returns the empty points whose neighbours are all the current player's stones or offline, the edge counting as offline.
+    fn true_eyes(&self) -> BitSet;
+    /// Returns the moves a random playout may choose from. These are the legal moves,
+    /// except for filling in the current player's own true eyes, unless the opponent could play there too.
+    fn playout_moves(&self, board_history: &BoardHistory) -> BitSet {
+        let legal_for_opponent: BitSet = self.pass().legal_moves(board_history);
+        return self.legal_moves(board_history) & (!self.true_eyes() | legal_for_opponent);
+    }
+    /// Returns the board after the current player plays at `point`. Doesn't check legality.
+    fn play(&self, point: usize) -> Self;
+    /// Returns the board after the current player passes.
+    fn pass(&self) -> Self;
+    /// Returns the points where playing is pointless for either player, same as settled_points.
+    /// Not cheap, so it's found once per evaluation and shared by all of its playouts.
+    fn settled_points(&self) -> SettledPoints;
+    /// Returns the points that count for `player` if the game ended now, by the same rules as final_score.
+    /// These are the player's stones, and the empty regions bordered by the player's stones only.
+    fn area(&self, player: Player) -> BitSet;
+    /// Scores the board as if the game ended now, same as final_score. Positive value = win for black.
+    fn score(&self) -> f64;
+}
+
+impl PlayoutBoard for Board {
+    fn player(&self) -> Player {
+        return self.player;
+    }
+    fn opponent_passed(&self) -> bool {
+        return self.opponent_passed;
+    }
+    fn point_count(&self) -> usize {
+        return self.board.len();
+    }
+    fn hash(&self) -> u64 {
+        return self.hash;
+    }
+    fn legal_moves(&self, board_history: &BoardHistory) -> BitSet {
+        return get_legal_moves(self, board_history);
+    }
+    fn true_eyes(&self) -> BitSet {
+        let mut result: BitSet = BitSet::new();
+        for point in 0..self.board.len() {
+            if self.board[point] != PointState::Empty as u8 {
+                continue;
+            }
+            let mut true_eye: bool = true;
+            for adjacent_point in get_adjacent_points(point, self) {
+                // It's not a true eye, if there's an adjacent empty square or enemy piece
+                if self.board[adjacent_point] == !self.player as u8 || self.board[adjacent_point] == PointState::Empty as u8 {
+                    true_eye = false;
+                    break;
+                }
+            }
+            if true_eye {
+                result.insert(point);
+            }
+        }
+        return result;
+    }
+    fn play(&self, point: usize) -> Self {
+        return make_move(Point::from_index(point, self.board.len()), self);
+    }
+    fn pass(&self) -> Self {
+        return pass_move(self);
+    }
+    fn settled_points(&self) -> SettledPoints {
+        return settled_points(self);
+    }
+    fn area(&self, player: Player) -> BitSet {
+        return BitBoard::from(self).area(player);
+    }
+    fn score(&self) -> f64 {
+        return final_score(self);
+    }
+}
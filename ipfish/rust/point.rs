@@ -0,0 +1,152 @@
+use crate::bitset::BitSet;
+
+// The letters of the columns. Go skips the letter I, so it can't be mixed up with J.
+const COLUMN_LABELS: &[u8] = b"abcdefghjklmnopqrstuvwxyz";
+
+/// A point on the board, its index into Board::board.
+/// The points go row by row, and the rows count up from the bottom like Bitburner's y coordinate, so the index is y * size + x.
+/// Points are only made from coordinates or indexes that are checked to be on the board.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Point(usize);
+
+impl Point {
+    /// The point at an index into Board::board.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index, below the number of points.
+    /// * `point_count` - The number of points on the board, the size squared.
+    pub fn from_index(index: usize, point_count: usize) -> Point {
+        assert!(index < point_count, "{} is not a point on a board with {} points", index, point_count);
+        return Point(index);
+    }
+
+    /// The index of the point into Board::board.
+    pub fn index(self) -> usize {
+        return self.0;
+    }
+
+    /// The point at Bitburner's `x` and `y` coordinates, the column from the left and the row from the bottom.
+    pub fn from_xy(x: usize, y: usize, size: usize) -> Point {
+        assert!(x < size && y < size, "({}, {}) is not on a board of size {}", x, y, size);
+        return Point(y * size + x);
+    }
+
+    /// Bitburner's `x` coordinate of the point, the column counting from the left.
+    pub fn x(self, size: usize) -> usize {
+        return self.0 % size;
+    }
+
+    /// Bitburner's `y` coordinate of the point, the row counting from the bottom.
+    pub fn y(self, size: usize) -> usize {
+        return self.0 / size;
+    }
+
+    /// The label of the point, the letter of its column followed by its row counting from 1 at the bottom, like c3.
+    pub fn label(self, size: usize) -> String {
+        return format!("{}{}", COLUMN_LABELS[self.x(size)] as char, self.y(size) + 1);
+    }
+
+    /// The point with a label like c3, in either case. None if it's not a label of a point on the board.
+    pub fn from_label(label: &str, size: usize) -> Option<Point> {
+        let lowercase: String = label.trim().to_ascii_lowercase();
+        let column: u8 = *lowercase.as_bytes().first()?;
+        let x: usize = COLUMN_LABELS.iter().position(|letter| *letter == column)?;
+        let y: usize = lowercase[1..].parse::<usize>().ok()?.checked_sub(1)?;
+        if x >= size || y >= size {
+            return None;
+        }
+        return Some(Point::from_xy(x, y, size));
+    }
+}
+
+/// A move, either playing a stone on a point or passing.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Move {
+    Play(Point),
+    Pass,
+}
+
+impl Move {
+    /// The move at an index into the analysis, where passing comes after the last point.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index, from 0 up to and including the number of points.
+    /// * `point_count` - The number of points on the board, the size squared.
+    pub fn from_index(index: usize, point_count: usize) -> Move {
+        assert!(index <= point_count, "{} is not a move on a board with {} points", index, point_count);
+        if index == point_count {
+            return Move::Pass;
+        }
+        return Move::Play(Point::from_index(index, point_count));
+    }
+
+    /// The index of the move into the analysis, see from_index.
+    pub fn index(self, point_count: usize) -> usize {
+        return match self {
+            Move::Play(point) => point.index(),
+            Move::Pass => point_count,
+        };
+    }
+
+    /// The point the move plays on, None for passing.
+    pub fn point(self) -> Option<Point> {
+        return match self {
+            Move::Play(point) => Some(point),
+            Move::Pass => None,
+        };
+    }
+
+    /// The label of the move, the point's label or pass.
+    pub fn label(self, size: usize) -> String {
+        return match self {
+            Move::Play(point) => point.label(size),
+            Move::Pass => String::from("pass"),
+        };
+    }
+
+    /// The move with a label like c3 or pass, in either case. None if it's not a move on the board.
+    pub fn from_label(label: &str, size: usize) -> Option<Move> {
+        if label.trim().eq_ignore_ascii_case("pass") {
+            return Some(Move::Pass);
+        }
+        return Point::from_label(label, size).map(Move::Play);
+    }
+}
+
+/// The moves that play on the points in the set, like the legal moves, in order. Passing isn't one of them.
+///
+/// # Arguments
+///
+/// * `points` - The points to play on.
+/// * `point_count` - The number of points on the board, the size squared.
+pub fn moves_on(points: BitSet, point_count: usize) -> impl Iterator<Item = Move> {
+    return points.into_iter().map(move |point| Move::Play(Point::from_index(point, point_count)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn points_convert_both_ways() {
+        // j is the ninth column, there's no i.
+        let point: Point = Point::from_xy(8, 2, 9);
+        assert_eq!(point.index(), 26);
+        assert_eq!(Point::from_index(26, 81), point);
+        assert_eq!((point.x(9), point.y(9)), (8, 2));
+        assert_eq!(point.label(9), "j3");
+        assert_eq!(Point::from_label("J3", 9), Some(point));
+        assert_eq!(Point::from_label("i3", 9), None);
+        assert_eq!(Point::from_label("a10", 9), None);
+        assert_eq!(Point::from_label("a0", 9), None);
+
+        assert_eq!(Move::from_index(81, 81), Move::Pass);
+        assert_eq!(Move::from_index(26, 81), Move::Play(point));
+        assert_eq!(Move::Pass.index(81), 81);
+        assert_eq!(Move::Pass.point(), None);
+        assert_eq!(Move::from_label("Pass", 9), Some(Move::Pass));
+        assert_eq!(Move::from_index(0, 81).label(9), "a1");
+    }
+}
@@ -0,0 +1,90 @@
+/// The mean and variance of a batch of final scores, from black's point of view.
+/// Scores can be added one at a time, or whole batches merged, without keeping the scores themselves around.
+/// Scores can be weighted, a score with a weight of 2 counts the same as two games with that score.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ScoreStats {
+    /// The total weight of the scores, the number of games if they're unweighted.
+    pub weight: f64,
+    /// The weighted mean of the scores.
+    pub mean: f64,
+    // The weighted sum of the squared differences from the mean, see Welford's algorithm.
+    squared_deviations: f64,
+}
+
+impl ScoreStats {
+    pub fn new() -> ScoreStats {
+        return ScoreStats::default();
+    }
+
+    /// The stats of a single score, counting for `weight` games.
+    pub fn single(score: f64, weight: f64) -> ScoreStats {
+        let mut result: ScoreStats = ScoreStats::new();
+        result.add(score, weight);
+        return result;
+    }
+
+    /// Adds a score, counting for `weight` games.
+    pub fn add(&mut self, score: f64, weight: f64) {
+        self.merge(&ScoreStats {
+            weight: weight,
+            mean: score,
+            squared_deviations: 0.0,
+        });
+    }
+
+    /// Adds every score of another batch, with the parallel version of Welford's algorithm.
+    pub fn merge(&mut self, other: &ScoreStats) {
+        let weight: f64 = self.weight + other.weight;
+        if weight == 0.0 {
+            return;
+        }
+        let delta: f64 = other.mean - self.mean;
+        self.mean += delta * other.weight / weight;
+        self.squared_deviations += other.squared_deviations + delta * delta * self.weight * other.weight / weight;
+        self.weight = weight;
+    }
+
+    /// The weighted variance of the scores. Zero without any scores.
+    pub fn variance(&self) -> f64 {
+        if self.weight == 0.0 {
+            return 0.0;
+        }
+        return self.squared_deviations / self.weight;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merging_is_the_same_as_adding() {
+        let scores: [f64; 6] = [3.5, -2.5, 10.5, 0.5, -7.5, 4.5];
+        let mut added: ScoreStats = ScoreStats::new();
+        for score in scores {
+            added.add(score, 1.0);
+        }
+        let mut merged: ScoreStats = ScoreStats::new();
+        for batch in scores.chunks(4) {
+            let mut batch_stats: ScoreStats = ScoreStats::new();
+            for score in batch {
+                batch_stats.add(*score, 1.0);
+            }
+            merged.merge(&batch_stats);
+        }
+
+        let mean: f64 = scores.iter().sum::<f64>() / 6.0;
+        let variance: f64 = scores.iter().map(|score| (score - mean) * (score - mean)).sum::<f64>() / 6.0;
+        for stats in [added, merged] {
+            assert_eq!(stats.weight, 6.0);
+            assert!((stats.mean - mean).abs() < 1e-9);
+            assert!((stats.variance() - variance).abs() < 1e-9);
+        }
+
+        // A weight of 2 is the same as adding the score twice.
+        let mut twice: ScoreStats = ScoreStats::single(3.5, 1.0);
+        twice.add(3.5, 1.0);
+        assert_eq!(twice, ScoreStats::single(3.5, 2.0));
+        assert_eq!(ScoreStats::new().variance(), 0.0);
+    }
+}
@@ -0,0 +1,499 @@
+use rustc_hash::FxBuildHasher;
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::bitset::BitSet;
+use crate::board::{Board, BoardHistory};
+use crate::board_input::chain_without_liberties;
+use crate::get_legal_moves::get_legal_moves_strict;
+use crate::make_move::play_move;
+use crate::player::Player;
+use crate::point::{Move, Point};
+use crate::point_state::PointState;
+
+// The SGF property for offline points, which SGF has no notion of. It takes a list of points like AB and AW do.
+// Viewers that don't know it ignore it, so offline points show up as empty there.
+const OFFLINE_PROPERTY: &str = "OF";
+
+/// A whole game: the position it started from and every move played since.
+#[derive(Clone, Debug)]
+pub struct GameRecord {
+    /// The board the game started from, with its setup stones, offline points, komi and the player to move first.
+    pub setup: Board,
+    /// The moves played, in order.
+    pub moves: Vec<Move>,
+}
+
+impl GameRecord {
+    /// A game that starts at `setup`, without any moves yet.
+    pub fn new(setup: Board) -> GameRecord {
+        return GameRecord {
+            setup: setup,
+            moves: Vec::new(),
+        };
+    }
+
+    /// Plays the moves on the setup board, returning the current board and every state the board has been in.
+    /// The moves aren't checked, read_sgf does that for games that come from elsewhere.
+    pub fn replay(&self) -> (Board, BoardHistory) {
+        let mut board: Board = self.setup.clone();
+        let mut board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+        board_history.insert(board.hash);
+        for played in &self.moves {
+            board = play_move(*played, &board);
+            board_history.insert(board.hash);
+        }
+        return (board, board_history);
+    }
+}
+
+/// Why an SGF game couldn't be read, and where.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SgfError {
+    /// The byte offset into the SGF text the problem was found at.
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SgfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{} at byte {}", self.message, self.position);
+    }
+}
+
+impl std::error::Error for SgfError {}
+
+/// Writes a game as SGF, for archiving it and replaying it in standard viewers.
+/// The root node has the board size, komi, the setup stones and the player to move first if that's white.
+/// Offline points go in the custom OF property, a list of points like AB and AW. Passes are written as empty moves.
+/// Whether the opponent had passed right before the setup isn't recorded.
+///
+/// # Arguments
+///
+/// * `game` - The game to write.
+pub fn write_sgf(game: &GameRecord) -> String {
+    let setup: &Board = &game.setup;
+    let mut result: String = format!("(;GM[1]FF[4]CA[UTF-8]AP[ipfish]SZ[{}]KM[{}]", setup.size, setup.komi);
+    if setup.player == Player::White {
+        result.push_str("PL[W]");
+    }
+    for (property, state) in [("AB", PointState::Black), ("AW", PointState::White), (OFFLINE_PROPERTY, PointState::Offline)] {
+        let points: Vec<Point> = (0..setup.board.len())
+            .filter(|point| setup.board[*point] == state as u8)
+            .map(|point| Point::from_index(point, setup.board.len()))
+            .collect();
+        if points.is_empty() {
+            continue;
+        }
+        result.push_str(property);
+        for point in points {
+            result.push_str(&format!("[{}]", point_to_sgf(point, setup.size)));
+        }
+    }
+    result.push('\n');
+
+    let mut player: Player = setup.player;
+    for played in &game.moves {
+        let colour: &str = if player == Player::Black { "B" } else { "W" };
+        let coordinates: String = match played {
+            Move::Play(point) => point_to_sgf(*point, setup.size),
+            Move::Pass => String::new(),
+        };
+        result.push_str(&format!(";{}[{}]", colour, coordinates));
+        player = !player;
+    }
+    result.push_str(")\n");
+    return result;
+}
+
+/// Reads an SGF game, as written by write_sgf or by other programs.
+/// Only the main line is read, other variations are skipped. Properties other than the ones write_sgf writes are ignored.
+/// Every move is checked for legality, including superko, and moves must alternate between the players.
+/// Setup stones are only allowed before the first move, and without PL the first move decides who starts.
+///
+/// # Arguments
+///
+/// * `sgf` - The SGF text, holding a single game.
+pub fn read_sgf(sgf: &str) -> Result<GameRecord, SgfError> {
+    let mut parser: Parser = Parser {
+        text: sgf.as_bytes(),
+        position: 0,
+    };
+    let mut nodes: Vec<Node> = Vec::new();
+    parser.skip_whitespace();
+    parser.game_tree(&mut nodes)?;
+    if nodes.is_empty() {
+        return Err(SgfError {
+            position: 0,
+            message: String::from("The game has no nodes"),
+        });
+    }
+
+    let size: usize = match nodes[0].property("SZ") {
+        Some(size) => size.number::<usize>()?,
+        None => 19,
+    };
+    if size == 0 || size.checked_mul(size).is_none_or(|points| points > BitSet::CAPACITY) {
+        return Err(nodes[0].property("SZ").unwrap().error(&format!("Board size {} isn't supported", size)));
+    }
+    let komi: f64 = match nodes[0].property("KM") {
+        Some(komi) => komi.number::<f64>()?,
+        None => 0.0,
+    };
+
+    // The setup is everything before the first move.
+    let first_move: usize = nodes.iter().position(|node| node.move_property().is_some()).unwrap_or(nodes.len());
+    let mut points: Vec<u8> = vec![PointState::Empty as u8; size * size];
+    // The property that last set up each point, to say where a bad setup went wrong.
+    let mut set_up_by: Vec<Option<&Property>> = vec![None; size * size];
+    let mut player: Option<Player> = None;
+    for node in &nodes[..first_move] {
+        for property in &node.properties {
+            let state: PointState = match property.name.as_str() {
+                "AB" => PointState::Black,
+                "AW" => PointState::White,
+                "AE" => PointState::Empty,
+                OFFLINE_PROPERTY => PointState::Offline,
+                "PL" => {
+                    player = Some(property.player()?);
+                    continue;
+                }
+                _ => continue,
+            };
+            for point in property.points(size)? {
+                points[point.index()] = state as u8;
+                set_up_by[point.index()] = Some(property);
+            }
+        }
+    }
+    for node in &nodes[first_move..] {
+        if let Some(property) = node
+            .properties
+            .iter()
+            .find(|property| ["AB", "AW", "AE", OFFLINE_PROPERTY, "PL"].contains(&property.name.as_str()))
+        {
+            return Err(property.error("Setup after the first move isn't supported"));
+        }
+    }
+    if player.is_none() && first_move < nodes.len() {
+        player = Some(nodes[first_move].move_property().unwrap().player()?);
+    }
+
+    let mut game: GameRecord = GameRecord::new(Board::new(points.into_boxed_slice(), size, player.unwrap_or(Player::Black), komi, false));
+    if let Some(point) = chain_without_liberties(&game.setup) {
+        let label: String = Point::from_index(point, size * size).label(size);
+        return Err(set_up_by[point]
+            .expect("Only setup properties add stones")
+            .error(&format!("The chain at {} has no liberties", label)));
+    }
+    let mut board: Board = game.setup.clone();
+    let mut board_history: BoardHistory = HashSet::with_hasher(FxBuildHasher);
+    board_history.insert(board.hash);
+    for node in &nodes[first_move..] {
+        let property: &Property = match node.move_property() {
+            Some(property) => property,
+            None => continue,
+        };
+        if property.player()? != board.player {
+            return Err(property.error(&format!("It's not {}'s turn", property.name)));
+        }
+        let played: Move = property.played(size)?;
+        if let Move::Play(point) = played
+            && !get_legal_moves_strict(&board, &board_history).contains(point.index())
+        {
+            return Err(property.error(&format!("{} is an illegal move", property.values[0].1)));
+        }
+        board = play_move(played, &board);
+        board_history.insert(board.hash);
+        game.moves.push(played);
+    }
+    return Ok(game);
+}
+
+/// Reads an SGF game and plays it out, see read_sgf and GameRecord::replay.
+pub fn board_from_sgf(sgf: &str) -> Result<(Board, BoardHistory), SgfError> {
+    return Ok(read_sgf(sgf)?.replay());
+}
+
+/// The SGF coordinates of a point. SGF counts the rows from the top, while our rows count from the bottom.
+fn point_to_sgf(point: Point, size: usize) -> String {
+    let row: usize = size - 1 - point.y(size);
+    return [(b'a' + point.x(size) as u8) as char, (b'a' + row as u8) as char].iter().collect();
+}
+
+/// The point at SGF coordinates, None if they're not on the board.
+fn point_from_sgf(coordinates: &str, size: usize) -> Option<Point> {
+    let letters: &[u8] = coordinates.as_bytes();
+    if letters.len() != 2 || !letters.iter().all(|letter| letter.is_ascii_lowercase()) {
+        return None;
+    }
+    let x: usize = (letters[0] - b'a') as usize;
+    let row: usize = (letters[1] - b'a') as usize;
+    if x >= size || row >= size {
+        return None;
+    }
+    return Some(Point::from_xy(x, size - 1 - row, size));
+}
+
+struct Property {
+    name: String,
+    // The values with the byte offsets they start at, escapes already removed.
+    values: Vec<(usize, String)>,
+}
+
+impl Property {
+    fn error(&self, message: &str) -> SgfError {
+        return SgfError {
+            position: self.values[0].0,
+            message: String::from(message),
+        };
+    }
+
+    fn number<T: std::str::FromStr>(&self) -> Result<T, SgfError> {
+        return self.values[0]
+            .1
+            .trim()
+            .parse::<T>()
+            .map_err(|_| self.error(&format!("{} isn't a valid {}", self.values[0].1, self.name)));
+    }
+
+    /// The player of a B or W move, or of PL.
+    fn player(&self) -> Result<Player, SgfError> {
+        let colour: &str = if self.name == "PL" { self.values[0].1.trim() } else { self.name.as_str() };
+        return match colour {
+            "B" => Ok(Player::Black),
+            "W" => Ok(Player::White),
+            _ => Err(self.error(&format!("{} isn't a player", colour))),
+        };
+    }
+
+    /// The points of a list of points, expanding rectangles like aa:cc.
+    fn points(&self, size: usize) -> Result<Vec<Point>, SgfError> {
+        let mut result: Vec<Point> = Vec::new();
+        for (position, value) in &self.values {
+            let error = || SgfError {
+                position: *position,
+                message: format!("{} isn't a point on the board", value),
+            };
+            let (from, to): (&str, &str) = value.split_once(':').unwrap_or((value, value));
+            let from: Point = point_from_sgf(from, size).ok_or_else(error)?;
+            let to: Point = point_from_sgf(to, size).ok_or_else(error)?;
+            // The corners should be the top left and the bottom right one, but any two opposite corners make the same rectangle.
+            let (left, right): (usize, usize) = (from.x(size).min(to.x(size)), from.x(size).max(to.x(size)));
+            let (bottom, top): (usize, usize) = (from.y(size).min(to.y(size)), from.y(size).max(to.y(size)));
+            for y in bottom..=top {
+                for x in left..=right {
+                    result.push(Point::from_xy(x, y, size));
+                }
+            }
+        }
+        return Ok(result);
+    }
+
+    /// The move of a B or W property. Old SGF versions pass with tt.
+    fn played(&self, size: usize) -> Result<Move, SgfError> {
+        let value: &str = self.values[0].1.as_str();
+        if value.is_empty() || (value == "tt" && size <= 19) {
+            return Ok(Move::Pass);
+        }
+        return point_from_sgf(value, size)
+            .map(Move::Play)
+            .ok_or_else(|| self.error(&format!("{} isn't a point on the board", value)));
+    }
+}
+
+struct Node {
+    properties: Vec<Property>,
+}
+
+impl Node {
+    fn property(&self, name: &str) -> Option<&Property> {
+        return self.properties.iter().find(|property| property.name == name);
+    }
+
+    fn move_property(&self) -> Option<&Property> {
+        return self.property("B").or_else(|| self.property("W"));
+    }
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> SgfError {
+        return SgfError {
+            position: self.position,
+            message: String::from(message),
+        };
+    }
+
+    fn peek(&self) -> Option<u8> {
+        return self.text.get(self.position).copied();
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|character| character.is_ascii_whitespace()) {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, character: u8) -> Result<(), SgfError> {
+        if self.peek() != Some(character) {
+            return Err(self.error(&format!("Expected {}", character as char)));
+        }
+        self.position += 1;
+        self.skip_whitespace();
+        return Ok(());
+    }
+
+    /// Reads a game tree, adding the nodes of its main line to `nodes`. Variations other than the first are skipped.
+    fn game_tree(&mut self, nodes: &mut Vec<Node>) -> Result<(), SgfError> {
+        self.expect(b'(')?;
+        while self.peek() == Some(b';') {
+            self.position += 1;
+            self.skip_whitespace();
+            nodes.push(self.node()?);
+        }
+        let mut first_variation: bool = true;
+        while self.peek() == Some(b'(') {
+            if first_variation {
+                self.game_tree(nodes)?;
+            } else {
+                self.game_tree(&mut Vec::new())?;
+            }
+            first_variation = false;
+        }
+        return self.expect(b')');
+    }
+
+    fn node(&mut self) -> Result<Node, SgfError> {
+        let mut properties: Vec<Property> = Vec::new();
+        while self.peek().is_some_and(|character| character.is_ascii_alphabetic()) {
+            let mut name: String = String::new();
+            while let Some(character) = self.peek().filter(|character| character.is_ascii_alphabetic()) {
+                // Old SGF versions mix in lower case letters, like AddBlack for AB.
+                if character.is_ascii_uppercase() {
+                    name.push(character as char);
+                }
+                self.position += 1;
+            }
+            self.skip_whitespace();
+            let mut values: Vec<(usize, String)> = Vec::new();
+            while self.peek() == Some(b'[') {
+                self.position += 1;
+                values.push((self.position, self.value()?));
+                self.skip_whitespace();
+            }
+            if values.is_empty() {
+                return Err(self.error(&format!("{} has no value", name)));
+            }
+            properties.push(Property { name: name, values: values });
+        }
+        return Ok(Node { properties: properties });
+    }
+
+    /// Reads a property value up to the closing bracket, leaving out the escaping backslashes.
+    fn value(&mut self) -> Result<String, SgfError> {
+        let mut result: Vec<u8> = Vec::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error("Unterminated property value")),
+                Some(b']') => break,
+                Some(b'\\') => {
+                    self.position += 1;
+                    if let Some(character) = self.peek() {
+                        result.push(character);
+                        self.position += 1;
+                    }
+                }
+                Some(character) => {
+                    result.push(character);
+                    self.position += 1;
+                }
+            }
+        }
+        self.position += 1;
+        return Ok(String::from_utf8_lossy(&result).into_owned());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board_from_string::board_from_string;
+
+    #[test]
+    fn games_round_trip() {
+        let setup: Board = Board::new(
+            board_from_string(
+                "
+    #X...
+    .....
+    ..O..
+    .....
+    ....#
+    ",
+                5,
+            ),
+            5,
+            Player::White,
+            5.5,
+            false,
+        );
+        let mut game: GameRecord = GameRecord::new(setup);
+        // White plays a1, black passes, white plays d4.
+        game.moves = vec![Move::from_index(0, 25), Move::Pass, Move::from_index(18, 25)];
+        let sgf: String = write_sgf(&game);
+        assert_eq!(
+            sgf,
+            "(;GM[1]FF[4]CA[UTF-8]AP[ipfish]SZ[5]KM[5.5]PL[W]AB[ba]AW[cc]OF[ee][aa]\n;W[ae];B[];W[db])\n"
+        );
+
+        let read: GameRecord = read_sgf(&sgf).unwrap();
+        assert_eq!(read.setup.board, game.setup.board);
+        assert_eq!(read.setup.player, Player::White);
+        assert_eq!(read.setup.komi, 5.5);
+        assert_eq!(read.moves, game.moves);
+
+        let (board, board_history): (Board, BoardHistory) = board_from_sgf(&sgf).unwrap();
+        assert_eq!(board.hash, game.replay().0.hash);
+        assert_eq!(board.player, Player::Black);
+        assert_eq!(board_history.len(), 3);
+    }
+
+    #[test]
+    fn other_programs_games_can_be_read() {
+        // Variations, comments with escaped brackets, rectangles of setup stones, one with its corners the wrong way round,
+        // and an old style pass.
+        let sgf: &str = "(;FF[3]SZ[5]KM[6.5]C[A comment \\] with a bracket]AddBlack[aa:bb]AW[be:ad]
+            ;B[cc](;W[dd];B[tt])(;W[ee]))";
+        let game: GameRecord = read_sgf(sgf).unwrap();
+        assert_eq!(game.setup.player, Player::Black);
+        assert_eq!(game.moves, vec![Move::from_index(12, 25), Move::from_index(8, 25), Move::Pass]);
+        for point in [20, 21, 15, 16] {
+            assert_eq!(game.setup.board[point], PointState::Black as u8);
+        }
+        for point in [0, 1, 5, 6] {
+            assert_eq!(game.setup.board[point], PointState::White as u8);
+        }
+    }
+
+    #[test]
+    fn bad_games_are_rejected() {
+        let error: SgfError = read_sgf("(;SZ[5];B[cc];B[dd])").unwrap_err();
+        assert_eq!(error.position, 16);
+        assert_eq!(error.message, "It's not B's turn");
+        assert_eq!(read_sgf("(;SZ[5];B[cc];W[cc])").unwrap_err().message, "cc is an illegal move");
+        assert_eq!(read_sgf("(;SZ[5];B[zz])").unwrap_err().message, "zz isn't a point on the board");
+        assert_eq!(read_sgf("(;SZ[5];B[cc]").unwrap_err().message, "Expected )");
+        assert_eq!(read_sgf("(;SZ[25])").unwrap_err().message, "Board size 25 isn't supported");
+        assert_eq!(read_sgf("(;SZ[4294967296])").unwrap_err().message, "Board size 4294967296 isn't supported");
+
+        // The white stones take the last liberty of the black one in the corner.
+        let error: SgfError = read_sgf("(;SZ[5]AB[ae]AW[be][ad])").unwrap_err();
+        assert_eq!(error.message, "The chain at a1 has no liberties");
+        assert_eq!(error.position, 10);
+    }
+}
@@ -0,0 +1,105 @@
+use crate::RNG;
+use crate::board::{Board, BoardHistory};
+use crate::clock::Deadline;
+use crate::ownership::Ownership;
+use crate::point::Move;
+
+/// The result of analyzing a board, the same whichever strategy did the analysis.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Analysis {
+    /// How good every move is, indexed by point with passing last. Higher number = better move.
+    /// Moves that shouldn't be played, or weren't looked at, are -infinity.
+    pub scores: Vec<f64>,
+    /// Who the points are expected to belong to at the end of the game, if the analysis played any games out.
+    pub ownership: Option<Ownership>,
+    /// How many points the player to move is expected to win by after every move, indexed like the scores.
+    /// NaN for moves that weren't looked at. None if the strategy doesn't estimate the score.
+    pub score_leads: Option<Vec<f64>>,
+}
+
+impl Analysis {
+    /// An analysis in which none of the moves on the board have been looked at yet.
+    pub fn new(board: &Board) -> Analysis {
+        return Analysis {
+            scores: vec![f64::NEG_INFINITY; board.board.len() + 1],
+            ownership: None,
+            score_leads: None,
+        };
+    }
+
+    /// An analysis that says to play `played`, and nothing else.
+    pub fn only(board: &Board, played: Move, score: f64) -> Analysis {
+        let mut analysis: Analysis = Analysis::new(board);
+        analysis.set_score(played, score);
+        return analysis;
+    }
+
+    /// The number of points on the board the analysis is of.
+    fn point_count(&self) -> usize {
+        return self.scores.len() - 1;
+    }
+
+    /// How good the move is.
+    pub fn score(&self, played: Move) -> f64 {
+        return self.scores[played.index(self.point_count())];
+    }
+
+    pub fn set_score(&mut self, played: Move, score: f64) {
+        let index: usize = played.index(self.point_count());
+        self.scores[index] = score;
+    }
+
+    /// How many points the player to move is expected to win by after the move. NaN if it wasn't looked at, or there are no score leads.
+    pub fn score_lead(&self, played: Move) -> f64 {
+        return match &self.score_leads {
+            Some(score_leads) => score_leads[played.index(self.point_count())],
+            None => f64::NAN,
+        };
+    }
+
+    /// Sets the score lead of the move. If there weren't any score leads yet, the other moves' start out NaN.
+    pub fn set_score_lead(&mut self, played: Move, lead: f64) {
+        let index: usize = played.index(self.point_count());
+        let move_count: usize = self.scores.len();
+        self.score_leads.get_or_insert_with(|| vec![f64::NAN; move_count])[index] = lead;
+    }
+
+    /// Every move on the board, playing on each point in order and then passing.
+    pub fn moves(&self) -> impl Iterator<Item = Move> + use<> {
+        let point_count: usize = self.point_count();
+        return (0..=point_count).map(move |index| Move::from_index(index, point_count));
+    }
+
+    /// The move with the highest score, the first one if several tie. Passing wins ties, same as in javascript.
+    pub fn best_move(&self) -> Move {
+        let mut best: Move = Move::Pass;
+        for played in self.moves() {
+            if self.score(played) > self.score(best) {
+                best = played;
+            }
+        }
+        return best;
+    }
+}
+
+/// A way of analyzing a board.
+pub trait Strategy {
+    /// Analyzes the board, searching until the deadline.
+    ///
+    /// # Arguments
+    ///
+    /// * `board` - The board to analyze. Whether the opponent passed last turn comes from the board.
+    /// * `board_history` - The board history used for superko, including the board itself.
+    /// * `deadline` - When the analysis has to be finished by.
+    /// * `rng` - RNG for strategies that need it.
+    fn analyze(&mut self, board: &Board, board_history: &BoardHistory, deadline: Deadline, rng: &mut RNG) -> Analysis;
+}
+
+/// Gives up, and passes.
+pub struct PassStrategy;
+
+impl Strategy for PassStrategy {
+    fn analyze(&mut self, board: &Board, _board_history: &BoardHistory, _deadline: Deadline, _rng: &mut RNG) -> Analysis {
+        return Analysis::only(board, Move::Pass, 0.0);
+    }
+}
@@ -0,0 +1,187 @@
+use crate::bitset::BitSet;
+use crate::board::{Board, BoardHistory};
+use crate::clock::Deadline;
+use crate::get_adjacent_points::get_adjacent_points;
+use crate::get_legal_moves::get_legal_moves;
+use crate::make_move::play_move;
+use crate::pass_move::pass_move;
+use crate::player::Player;
+use crate::point::moves_on;
+
+// How many moves deep the reading goes. Deeper than this and the chain counts as escaped, ladders rarely run longer on ipvgo boards.
+const READING_DEPTH: u32 = 24;
+
+/// The outcome of reading out whether a chain can be captured.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Tactics {
+    /// The chain gets captured even if its owner moves first.
+    Captured,
+    /// Whoever moves first decides whether the chain gets captured.
+    Unsettled,
+    /// The chain can't be captured by ataris and ladders, even if the opponent moves first.
+    /// It might still die for lack of eyes, that's beyond this reading.
+    Safe,
+}
+
+/// Reads out whether the chain at `point` can be captured, with both players getting to move first.
+/// Only ataris, ladders and capturing the stones that do the chasing are read, so it's quick but shortsighted.
+/// If the deadline passes before the reading is done, the chain is Unsettled, as nothing is known for sure.
+///
+/// # Arguments
+///
+/// * `board` - The board state. Whose turn it is doesn't matter, both are read.
+/// * `board_history` - The board history used for superko, including the board itself.
+/// * `point` - A point of the chain, which must have a stone on it.
+/// * `deadline` - When to give up reading.
+pub fn read_chain(board: &Board, board_history: &BoardHistory, point: usize, deadline: &Deadline) -> Tactics {
+    let owner: Player = Player::from(board.board[point] as f64);
+    let captured_if_attacked: bool = attack(&to_move(board, !owner), board_history, point, READING_DEPTH, deadline);
+    if deadline.expired() {
+        return Tactics::Unsettled;
+    }
+    if !captured_if_attacked {
+        return Tactics::Safe;
+    }
+    let saved_if_defended: bool = defend(&to_move(board, owner), board_history, point, READING_DEPTH, deadline);
+    if deadline.expired() || saved_if_defended {
+        return Tactics::Unsettled;
+    }
+    return Tactics::Captured;
+}
+
+/// The board with `player` to move.
+fn to_move(board: &Board, player: Player) -> Board {
+    if board.player == player {
+        return board.clone();
+    }
+    return pass_move(board);
+}
+
+/// Returns true if the player to move, who doesn't own the chain at `point`, can capture it.
+/// Once the deadline passes the chain gets away, the caller can't trust the answer then.
+fn attack(board: &Board, board_history: &BoardHistory, point: usize, depth: u32, deadline: &Deadline) -> bool {
+    let liberties: BitSet = board.chain(point).liberties;
+    let legal_moves: BitSet = get_legal_moves(board, board_history);
+    if liberties.len() == 1 {
+        return legal_moves.contains(liberties.first().unwrap());
+    }
+    // Chains with more liberties than that aren't chased, they get away unless they have no eyes.
+    if liberties.len() > 2 || depth == 0 || deadline.expired() {
+        return false;
+    }
+    for liberty in moves_on(liberties & legal_moves, board.board.len()) {
+        if !defend(&play_move(liberty, board), board_history, point, depth - 1, deadline) {
+            return true;
+        }
+    }
+    return false;
+}
+
+/// Returns true if the player to move, who owns the chain at `point`, can keep it from being captured.
+/// Once the deadline passes the chain gets away, like in attack.
+fn defend(board: &Board, board_history: &BoardHistory, point: usize, depth: u32, deadline: &Deadline) -> bool {
+    let liberties: BitSet = board.chain(point).liberties;
+    if depth == 0 || deadline.expired() {
+        return true;
+    }
+    // With liberties to spare, maybe nothing needs doing.
+    if liberties.len() >= 2 && !attack(&pass_move(board), board_history, point, depth - 1, deadline) {
+        return true;
+    }
+    // Run away by extending, or capture a chasing chain that's in atari itself.
+    let mut defences: BitSet = liberties;
+    for stone in board.chain(point).stones {
+        for adjacent_point in get_adjacent_points(stone, board) {
+            if board.board[adjacent_point] == !board.player as u8 && board.chain(adjacent_point).liberties.len() == 1 {
+                defences |= board.chain(adjacent_point).liberties;
+            }
+        }
+    }
+    for defence in moves_on(defences & get_legal_moves(board, board_history), board.board.len()) {
+        if !attack(&play_move(defence, board), board_history, point, depth - 1, deadline) {
+            return true;
+        }
+    }
+    return false;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board_from_string::board_from_string;
+    use rustc_hash::FxBuildHasher;
+    use std::collections::HashSet;
+
+    fn read(board_string: &str, point: usize) -> Tactics {
+        let board: Board = Board::new(board_from_string(board_string, 7), 7, Player::Black, 5.5, false);
+        return read_chain(&board, &HashSet::with_hasher(FxBuildHasher), point, &Deadline::never());
+    }
+
+    #[test]
+    fn ladders_are_read_out() {
+        // The white stone at c3 is in atari, and running away only lands it in a ladder towards the edge.
+        let ladder: &str = "
+    .......
+    .......
+    .......
+    ..X....
+    .XOX...
+    ...X...
+    .......
+    ";
+        assert_eq!(read(ladder, 16), Tactics::Captured);
+
+        // A white stone in the ladder's path breaks it, so black has to capture right away.
+        let broken_ladder: &str = "
+    .......
+    .......
+    .......
+    ..X....
+    .XOX...
+    .O.X...
+    .......
+    ";
+        assert_eq!(read(broken_ladder, 16), Tactics::Unsettled);
+    }
+
+    #[test]
+    fn open_chains_are_safe() {
+        let open: &str = "
+    .......
+    .......
+    .......
+    ...O...
+    .......
+    .......
+    .......
+    ";
+        assert_eq!(read(open, 24), Tactics::Safe);
+    }
+
+    #[test]
+    fn reading_stops_at_the_deadline() {
+        let board: Board = Board::new(
+            board_from_string(
+                "
+    .......
+    .......
+    .......
+    ..X....
+    .XOX...
+    ...X...
+    .......
+    ",
+                7,
+            ),
+            7,
+            Player::Black,
+            5.5,
+            false,
+        );
+        // The ladder would be Captured, but there's no time to read it out.
+        assert_eq!(
+            read_chain(&board, &HashSet::with_hasher(FxBuildHasher), 16, &Deadline::after(0.0)),
+            Tactics::Unsettled
+        );
+    }
+}
@@ -0,0 +1,122 @@
+use crate::bitset::BitSet;
+use crate::board::Board;
+use crate::get_adjacent_points::get_adjacent_points;
+use crate::is_in_atari::is_in_atari;
+use crate::point_state::PointState;
+
+/// Random keys for every point and stone colour (black, white and offline, in that order).
+/// Generated at compile time so that hashes are the same between runs, and between wasm and native builds.
+static ZOBRIST_KEYS: [[u64; 3]; BitSet::CAPACITY] = generate_keys();
+
+const fn generate_keys() -> [[u64; 3]; BitSet::CAPACITY] {
+    let mut keys: [[u64; 3]; BitSet::CAPACITY] = [[0; 3]; BitSet::CAPACITY];
+    let mut state: u64 = 0x1f2e_3d4c_5b6a_7988;
+    let mut point: usize = 0;
+    while point < BitSet::CAPACITY {
+        let mut stone: usize = 0;
+        while stone < 3 {
+            // https://prng.di.unimi.it/splitmix64.c
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z: u64 = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            keys[point][stone] = z ^ (z >> 31);
+            stone += 1;
+        }
+        point += 1;
+    }
+    return keys;
+}
+
+/// Returns the key of a single point. Empty points don't contribute to the hash, so their key is 0.
+///
+/// # Arguments
+///
+/// * `point` - The point the stone is on.
+/// * `point_state` - The raw point state, as stored in the board.
+pub fn zobrist_key(point: usize, point_state: u8) -> u64 {
+    if point_state == PointState::Black as u8 {
+        return ZOBRIST_KEYS[point][0];
+    } else if point_state == PointState::White as u8 {
+        return ZOBRIST_KEYS[point][1];
+    } else if point_state == PointState::Offline as u8 {
+        return ZOBRIST_KEYS[point][2];
+    } else {
+        return 0;
+    }
+}
+
+/// Hashes a whole board position from scratch.
+/// Only needed when a board comes from outside, after that the hash is kept up to date by make_move.
+///
+/// # Arguments
+///
+/// * `board` - The raw points of the board.
+pub fn zobrist_hash(board: &[u8]) -> u64 {
+    let mut hash: u64 = 0;
+    for point in 0..board.len() {
+        hash ^= zobrist_key(point, board[point]);
+    }
+    return hash;
+}
+
+/// Returns the hash the board would have after playing at `point`, without actually making the move.
+/// This is what superko checks use, so they don't need to build a whole new board per candidate move.
+///
+/// # Arguments
+///
+/// * `point` - The point the current player would play at.
+/// * `board` - The state of the board before the move.
+pub fn hash_after_move(point: usize, board: &Board) -> u64 {
+    let mut hash: u64 = board.hash ^ zobrist_key(point, board.player as u8);
+
+    // Adjacent enemy groups in atari get captured, so their stones leave the hash.
+    let mut captured: BitSet = BitSet::new();
+    for adjacent_point in get_adjacent_points(point, board) {
+        if board.board[adjacent_point] == !board.player as u8 && is_in_atari(adjacent_point, board, point) {
+            captured |= board.chain(adjacent_point).stones;
+        }
+    }
+    for captured_point in captured {
+        hash ^= zobrist_key(captured_point, !board.player as u8);
+    }
+    return hash;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board_from_string::board_from_string;
+    use crate::make_move::make_move;
+    use crate::pass_move::pass_move;
+    use crate::player::Player;
+    use crate::point::Point;
+
+    #[test]
+    fn incremental_hash_matches_full_hash() {
+        let board: Box<[u8]> = board_from_string(
+            "
+    ...XO
+    XOOO.
+    XXXOO
+    .XOOO
+    X.#..
+    ",
+            5,
+        );
+        let board: Board = Board::new(board, 5, Player::Black, 7.5, false);
+
+        // Black captures the white stone in the corner.
+        let predicted_hash: u64 = hash_after_move(19, &board);
+        let board: Board = make_move(Point::from_index(19, 25), &board);
+        assert_eq!(board.board[24], PointState::Empty as u8, "The corner stone should have been captured");
+        assert_eq!(board.hash, predicted_hash);
+        assert_eq!(board.hash, zobrist_hash(&board.board));
+
+        let board: Board = pass_move(&board);
+        assert_eq!(board.hash, zobrist_hash(&board.board));
+
+        let board: Board = make_move(Point::from_index(1, 25), &board);
+        assert_eq!(board.hash, zobrist_hash(&board.board));
+    }
+}
@@ -1,6 +1,8 @@
 import { NS, GoOpponent } from "@ns";
 import { CurrentTurn } from "./getCurrentTurn";
 import { getBoardFromAPI } from "./getBoardFromAPI"
+import { pointIndex } from "./moveString"
+import type { StrategyChoice, RaveChoice, LifeStatus } from "@rust"
 
 
 export type BoardState = Uint8Array
@@ -19,14 +21,74 @@ export interface AnalaysisBoard {
   passed: boolean,
 }
 
+// Tunes the engine without recompiling it, see EngineConfig in the rust code. Anything left out keeps its default.
+export interface EngineSettings {
+  strategy?: StrategyChoice,
+  tengen_opening?: boolean,
+  guesstimate_share?: number,
+  guesstimate_min_simulations?: number,
+  guesstimate_max_simulations?: number,
+  minimax_threshold?: number,
+  pass_threshold?: number,
+  mcts_simulations?: number,
+  rave?: RaveChoice,
+  rave_equivalence?: number,
+  rave_bias?: number,
+  maximize_score?: boolean,
+}
+
+// The messages the analysis worker understands. The worker follows the game, so it can keep its search between moves.
+// Only analysis, group status and sgf requests get a response, and they carry an id the responses come back with,
+// so several can be in flight at once. Telling the worker about a move cancels the analysis in progress.
+export type WorkerRequest =
+  | { type: "newGame", board: AnalaysisBoard, settings: EngineSettings }
+  // The point is missing for a pass.
+  | { type: "ourMove", point?: number }
+  | { type: "opponentMove", point?: number }
+  | { type: "analysis", id: number, budget: number }
+  | { type: "groupStatus", id: number, board: AnalaysisBoard, budget: number }
+  | { type: "sgf", id: number }
+
+// What the worker responds to an analysis request with. Progress is sent every so often while searching,
+// the final analysis once the budget is used up, or cancelled if the game moves on first. A group status request gets the status of every chain,
+// an sgf request the game so far as SGF. Any request can get an error instead, saying what went wrong.
+// The id is the request's, errors of requests without one don't have it.
+export type WorkerResponse = { id?: number } & (
+  | { type: "progress" | "analysis", analysis: Analysis }
+  | { type: "cancelled" }
+  | { type: "groupStatus", groups: Group[] }
+  | { type: "sgf", sgf: string }
+  | { type: "error", message: string }
+)
+
+// What an analysis rejects with when the game moves on before it's done.
+export class AnalysisCancelled extends Error {
+  constructor() {
+    super("The analysis was cancelled, the game moved on")
+  }
+}
 
 export interface Analysis {
   analysis: Float64Array
   bestMove: number
+  // For every point, from -1 if it's expected to end up white's to 1 if it's expected to end up black's.
+  // Empty if the analysis didn't play any games out.
+  ownership: Float64Array
+  // How many points we're expected to win by after every move, NaN for moves that weren't looked at.
+  // Empty if the analysis didn't estimate the score.
+  scoreLeads: Float64Array
 }
 
-// eslint-disable-next-line @typescript-eslint/no-unused-vars
-const goAlphabet = [...'abcdefghjklmnopqrstuvwxyz'];
+// A chain of stones and whether it lives, see get_group_status in the rust code.
+export interface Group {
+  color: PointState
+  stones: number[]
+  liberties: number[]
+  inAtari: boolean
+  status: keyof typeof LifeStatus
+  // The share of the playouts the stones counted for their owner in.
+  ownership: number
+}
 
 // Represents only a single game of ipvgo
 export class Game {
@@ -37,12 +99,18 @@ export class Game {
   public turn : CurrentTurn
   private worker : Worker
   private opponentPassed : boolean = false
+  // How long to think about each move, in milliseconds.
+  public analysisBudget : number = 5000
+  // How long to spend on playouts for the group status, in milliseconds.
+  public groupStatusBudget : number = 500
+  // The requests waiting for their responses, by id.
+  private pendingRequests = new Map<number, (response : WorkerResponse) => void>()
+  private nextRequestId : number = 0
 
-  constructor(ns : NS, opponent : GoOpponent, boardSize :  5 | 7 | 9 | 13, worker : Worker) {
+  constructor(ns : NS, opponent : GoOpponent, boardSize :  5 | 7 | 9 | 13, worker : Worker, settings : EngineSettings = {}) {
     this.ns = ns
-    // Start a new game if there isn't one. Also start a new game if the current one is greater than 7x7.
-    // This is because our rust currently only supports 5x5 and maybe 7x7.
-    if (ns.go.getGameState().currentPlayer === "None" || ns.go.getBoardState().length > 5) {
+    // Start a new game if there isn't one.
+    if (ns.go.getGameState().currentPlayer === "None") {
       ns.go.resetBoardState(opponent, boardSize)
     }
     this.boardSize = ns.go.getBoardState().length as 5 | 7 | 9 | 13 | 19 // 19 is secret opponent
@@ -51,6 +119,20 @@ export class Game {
     this.turn = CurrentTurn.Black
 
     this.worker = worker
+    this.worker.onmessage = (event : MessageEvent<WorkerResponse>) => {
+      this.handleResponse(event.data)
+    }
+    this.worker.onerror = (event) => {
+      this.failPendingRequests(`Worker onerror triggered ${event.message}`)
+    }
+    this.worker.onmessageerror = (event) => {
+      this.failPendingRequests(`Worker onmessageerror triggered ${event.data}`)
+    }
+    this.postRequest({ type: "newGame", board: this.getAnalysisBoard(), settings: settings })
+  }
+
+  private postRequest(request : WorkerRequest) {
+    this.worker.postMessage(request)
   }
 
   public getBoard() : BoardState {
@@ -59,7 +141,7 @@ export class Game {
 
   // IMPORTANT: this is zero indexed
   public getPoint(row : number, column : number) : PointState {
-    return this.getBoard()[this.boardSize * row + column]
+    return this.getBoard()[pointIndex(column, row, this.boardSize)]
   }
 
   public async makeMove(row : number, column : number, boardCallback? : (boardState: BoardState) => void, analysisCallBack? : (analysisState: Analysis) => void) {
@@ -72,19 +154,21 @@ export class Game {
       this.boardHistory.push(boardAfterBlackMoved)
 
       const opponentMove = await responsePromise
+      this.postRequest({ type: "ourMove", point: pointIndex(column, row, this.boardSize) })
       const boardAfterWhiteMoved = getBoardFromAPI(this.ns)
       if(opponentMove.type === "move" && opponentMove.x !== null && opponentMove.y !== null) {
         this.opponentPassed = false
+        this.postRequest({ type: "opponentMove", point: pointIndex(opponentMove.x, opponentMove.y, this.boardSize) })
         if (boardCallback !== undefined) {
           boardCallback(boardAfterWhiteMoved)
         }
         this.boardHistory.push(boardAfterWhiteMoved)
       } else if(opponentMove.type === "pass") {
         this.opponentPassed = true
+        this.postRequest({ type: "opponentMove" })
       }
       if (analysisCallBack !== undefined) {
-        const newAnalaysisState = await this.analysis()
-        analysisCallBack(newAnalaysisState)
+        await this.updateAnalysis(analysisCallBack)
       }
     } catch (e) {
       // Currently e is a string unfortunately
@@ -102,16 +186,18 @@ export class Game {
 
   public async passTurn(boardCallback? : (boardState: BoardState) => void, analysisCallBack? : (analysisState: Analysis) => void) : Promise<boolean> {
     const opponentMove = await this.ns.go.passTurn()
+    this.postRequest({ type: "ourMove" })
     const boardAfterWhiteMoved = getBoardFromAPI(this.ns)
     if(opponentMove.type === "move" && opponentMove.x !== null && opponentMove.y !== null) {
+      this.opponentPassed = false
+      this.postRequest({ type: "opponentMove", point: pointIndex(opponentMove.x, opponentMove.y, this.boardSize) })
       if (boardCallback !== undefined) {
         boardCallback(boardAfterWhiteMoved)
       }
       this.boardHistory.push(boardAfterWhiteMoved)
     }
     if (analysisCallBack !== undefined) {
-      const newAnalaysisState = await this.analysis()
-      analysisCallBack(newAnalaysisState)
+      await this.updateAnalysis(analysisCallBack)
     }
 
     if (this.ns.go.getCurrentPlayer() === "None") {
@@ -129,37 +215,94 @@ export class Game {
     }
   }
 
-  private async realAnalysis() : Promise<Analysis> {
-    const analysisBoard : AnalaysisBoard = {
-      boardHistory: this.boardHistory,
-      komi: this.komi,
-      turn: this.turn,
-      passed: this.opponentPassed,
+  // Hands a response to the request it's for. Errors of requests nobody waits for, like telling the worker about a move, are logged.
+  private handleResponse(response : WorkerResponse) {
+    const pending = response.id === undefined ? undefined : this.pendingRequests.get(response.id)
+    if (pending !== undefined) {
+      pending(response)
+    } else if (response.type === "error") {
+      this.ns.print(`Worker error: ${response.message}`)
+    }
+  }
+
+  private failPendingRequests(message : string) {
+    for (const pending of this.pendingRequests.values()) {
+      pending({ type: "error", message: message })
     }
+  }
 
-    this.worker.postMessage(analysisBoard)
+  // Sends a request that gets a response, and settles once the response to it comes in.
+  // Every response to it goes through onResponse, which resolves the request once it has its answer.
+  // Errors reject it, and so does cancelling it, with AnalysisCancelled.
+  private request<T>(makeRequest : (id : number) => WorkerRequest, onResponse : (response : WorkerResponse, resolve : (value : T) => void) => void) : Promise<T> {
+    const id = this.nextRequestId++
     return new Promise((resolve, reject) => {
-      if (this.worker === undefined) {
-        reject("Worker non existent during analysis")
-      } else {
-        this.worker.onmessage = (event : MessageEvent<Analysis>) => {
-          resolve(event.data) 
-        }
-        this.worker.onerror = (event) => {
-          reject(`Worker onerror triggered ${event.message}`)
+      this.pendingRequests.set(id, (response) => {
+        if (response.type === "error") {
+          this.pendingRequests.delete(id)
+          reject(response.message)
+        } else if (response.type === "cancelled") {
+          this.pendingRequests.delete(id)
+          reject(new AnalysisCancelled())
+        } else {
+          onResponse(response, (value) => {
+            this.pendingRequests.delete(id)
+            resolve(value)
+          })
         }
-        this.worker.onmessageerror = (event) => {
-          reject(`Worker onmessageerror triggered ${event.data}`)
+      })
+      this.postRequest(makeRequest(id))
+    })
+  }
+
+  private async realAnalysis(progressCallback? : (analysisState: Analysis) => void) : Promise<Analysis> {
+    return this.request((id) => ({ type: "analysis", id: id, budget: this.analysisBudget }), (response, resolve : (analysis : Analysis) => void) => {
+      if (response.type === "progress") {
+        if (progressCallback !== undefined) {
+          progressCallback(response.analysis)
         }
+      } else if (response.type === "analysis") {
+        resolve(response.analysis)
       }
     })
   }
 
-  public async analysis() : Promise<Analysis> {
+  // The progress callback gets the analysis so far while the search is still going.
+  public async analysis(progressCallback? : (analysisState: Analysis) => void) : Promise<Analysis> {
     const analysisStart = performance.now()
-    const analysis = await this.realAnalysis()
+    const analysis = await this.realAnalysis(progressCallback)
     const analysisTime = performance.now() - analysisStart
     this.ns.tprint(`Completed analysis in ${this.ns.tFormat(analysisTime, true)}`)
     return analysis
   }
+
+  // Analyzes the board after a move for the UI. If another move is made before it's done, the analysis is cancelled,
+  // and the callback only hears about the analysis after that move.
+  private async updateAnalysis(analysisCallBack : (analysisState: Analysis) => void) {
+    try {
+      analysisCallBack(await this.analysis(analysisCallBack))
+    } catch (e) {
+      if (!(e instanceof AnalysisCancelled)) {
+        throw e
+      }
+    }
+  }
+
+  // Which chains are alive, dead or unsettled.
+  public async groupStatus() : Promise<Group[]> {
+    return this.request((id) => ({ type: "groupStatus", id: id, board: this.getAnalysisBoard(), budget: this.groupStatusBudget }), (response, resolve : (groups : Group[]) => void) => {
+      if (response.type === "groupStatus") {
+        resolve(response.groups)
+      }
+    })
+  }
+
+  // The game so far as SGF, for archiving it.
+  public async sgf() : Promise<string> {
+    return this.request((id) => ({ type: "sgf", id: id }), (response, resolve : (sgf : string) => void) => {
+      if (response.type === "sgf") {
+        resolve(response.sgf)
+      }
+    })
+  }
 }
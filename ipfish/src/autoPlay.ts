@@ -1,20 +1,37 @@
 import { GoOpponent, NS } from "@ns"
-import { Game } from "./Game"
+import { Analysis, Game, Group, PointState } from "./Game"
+import { moveString, pointX, pointY } from "./moveString"
 
 export async function autoPlay(ns : NS, boardSize: 5 | 7 | 9 | 13, opponent : GoOpponent, analysisWorker : Worker) : Promise<void> {
   while (true) {
     const squareCount = boardSize ** 2
     const game = new Game(ns, opponent, boardSize, analysisWorker)
+    // Explaining the moves comes out of the time for the move, so it doesn't slow the games down.
+    game.analysisBudget -= game.groupStatusBudget
     while (ns.go.getCurrentPlayer() !== "None") {
-      const analysis = await game.analysis()
+      let analysis : Analysis
+      try {
+        analysis = await game.analysis()
+      } catch (e) {
+        // The engine couldn't make sense of the board, playing on won't help.
+        ns.toast(`Analysis failed: ${e}`, ns.enums.ToastVariant.ERROR, 10000)
+        ns.print(`Analysis failed, gameplay stopped: ${e}`)
+        return
+      }
+      try {
+        explainMove(ns, analysis.bestMove, await game.groupStatus(), game.boardSize)
+      } catch (e) {
+        // The move can still be played without explaining it.
+        ns.print(`Group status failed: ${e}`)
+      }
       if (analysis.bestMove == squareCount) {
         const isGameOver = await game.passTurn()
         if (isGameOver) {
           break
         }
       } else {
-        const bestMoveColumn = Math.floor(analysis.bestMove % game.boardSize)
-        const bestMoveRow = Math.floor(analysis.bestMove / game.boardSize)
+        const bestMoveColumn = pointX(analysis.bestMove, game.boardSize)
+        const bestMoveRow = pointY(analysis.bestMove, game.boardSize)
         if (analysis.analysis[analysis.bestMove] < -0.2) {
           ns.tprint("Blunder detected, gameplay stopped!")
           ns.exit()
@@ -23,5 +40,31 @@ export async function autoPlay(ns : NS, boardSize: 5 | 7 | 9 | 13, opponent : Go
         await game.makeMove(bestMoveRow, bestMoveColumn)
       }
     }
+    await archiveGame(ns, game)
+  }
+}
+
+// Keeps the finished game as SGF, so it can be replayed in a viewer or fed back into the analysis.
+// Losing the record isn't worth stopping for, the next game is played either way.
+async function archiveGame(ns : NS, game : Game) {
+  const fileName = `ipfish/games/${Date.now()}.txt`
+  try {
+    ns.write(fileName, await game.sgf(), "w")
+    ns.print(`Game saved to ${fileName}`)
+  } catch (e) {
+    ns.print(`Saving the game failed: ${e}`)
+  }
+}
+
+// Logs the chains that aren't settled as alive, and which of them the move is a liberty of.
+function explainMove(ns : NS, move : number, groups : Group[], boardSize : number) {
+  for (const group of groups) {
+    if (group.status === "Alive") {
+      continue
+    }
+    const color = group.color === PointState.Black ? "Black" : "White"
+    const reason = group.liberties.includes(move) ? `, playing ${moveString(move, boardSize)} on a liberty` : ""
+    const atari = group.inAtari ? " in atari" : ""
+    ns.print(`${color} chain at ${moveString(group.stones[0], boardSize)} is ${group.status.toLowerCase()}${atari}${reason}`)
   }
 }
@@ -65,11 +65,13 @@ export async function ipfish(ns: NS, game : Game): Promise<void> {
   ns.ui.renderTail()
 
   const analysisState = await game.analysis()
+  const groups = await game.groupStatus()
 
 
   ns.printRaw(React.createElement(IpFish, {game: game,
                                            initalBoardState: game.getBoard(),
                                            initalAnalysisState: analysisState,
+                                           initialGroups: groups,
                                            komi: game.komi,
                                            initialTurn: game.turn,
                                           }))
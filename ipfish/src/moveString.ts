@@ -1,8 +1,25 @@
-export function moveString(moveNumber : number, boardSize : number) : string {
-  const goAlphabet = [...'abcdefghjklmnopqrstuvwxyz'];
+// The letters of the columns. Go skips the letter I, so it can't be mixed up with J.
+export const goAlphabet = [...'abcdefghjklmnopqrstuvwxyz'];
+
+// Points are numbered row by row, with the rows counting up from the bottom like Bitburner's y, the same as in the rust code.
+export function pointIndex(x : number, y : number, boardSize : number) : number {
+  return y * boardSize + x
+}
 
-  const column = moveNumber % boardSize
-  const row = Math.floor(moveNumber / boardSize)
+// Bitburner's x coordinate of a point, the column counting from the left.
+export function pointX(point : number, boardSize : number) : number {
+  return point % boardSize
+}
 
-  return `${goAlphabet[column]}${row + 1}`
+// Bitburner's y coordinate of a point, the row counting from the bottom.
+export function pointY(point : number, boardSize : number) : number {
+  return Math.floor(point / boardSize)
+}
+
+// The label of a move like c3, passing is the point one past the end of the board.
+export function moveString(moveNumber : number, boardSize : number) : string {
+  if (moveNumber === boardSize ** 2) {
+    return "pass"
+  }
+  return `${goAlphabet[pointX(moveNumber, boardSize)]}${pointY(moveNumber, boardSize) + 1}`
 }
@@ -1,7 +1,8 @@
-import { BoardState, Game, Analysis } from "@/Game"
+import { BoardState, Game, Analysis, Group } from "@/Game"
 import css from "./css/IpFish.module.css"
 import GoPoint from "./GoPoint"
 import PassButton from "./PassButton"
+import { goAlphabet, pointIndex } from "@/moveString"
 
 interface GoBoardProps {
   gameClass : Game
@@ -10,12 +11,13 @@ interface GoBoardProps {
   boardSize : 5 | 7 | 9 | 13 | 19
   analysisState : Analysis
   updateAnalysisState : (analysisState: Analysis) => void
+  groups : Group[]
+  updateGroups : (groups: Group[]) => void
 }
 
-// The go alphabet skips the letter I for some reason
-const goAlphabet = [...'abcdefghjklmnopqrstuvwxyz'];
-
 function GoBoard(props : GoBoardProps) {
+  // The stones of the chains that might not live get highlighted.
+  const weakStones = new Set(props.groups.filter((group) => group.status !== "Alive").flatMap((group) => group.stones))
   return (
     <>
       <div className={css.boardBackground} >
@@ -24,13 +26,16 @@ function GoBoard(props : GoBoardProps) {
             return <tr key={row}>
               <td className={css.numericLabel}>{props.boardSize - row}</td>
               {[...Array(props.boardSize).keys()].map((column) => {
+                const point = pointIndex(column, props.boardSize - row - 1, props.boardSize)
                 return <GoPoint
                           key={column}
-                          pointState={props.boardState[(props.boardSize - row - 1) * props.boardSize + column]}
-                          evaluation={props.analysisState.analysis[(props.boardSize - row - 1) * props.boardSize + column]}
-                          bestMove={(props.boardSize - row - 1) * props.boardSize + column === props.analysisState.bestMove}
+                          pointState={props.boardState[point]}
+                          evaluation={props.analysisState.analysis[point]}
+                          bestMove={point === props.analysisState.bestMove}
+                          weak={weakStones.has(point)}
                           updateBoardState={props.updateBoardState}
                           updateAnalysisState={props.updateAnalysisState}
+                          updateGroups={props.updateGroups}
                           gameClass={props.gameClass}
                           row={props.boardSize - row - 1}
                           column={column}
@@ -46,10 +51,11 @@ function GoBoard(props : GoBoardProps) {
                 gameClass={props.gameClass}
                 updateBoardState={props.updateBoardState}
                 updateAnalysisState={props.updateAnalysisState}
+                updateGroups={props.updateGroups}
               />
             </td>
-            {[...Array(props.boardSize).keys()].map((row) => {
-              return <td className={css.alphabeticLabel}>{goAlphabet[row]}</td>
+            {[...Array(props.boardSize).keys()].map((column) => {
+              return <td className={css.alphabeticLabel}>{goAlphabet[column]}</td>
             })}
           </tr>
         </table>
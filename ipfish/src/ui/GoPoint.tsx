@@ -1,4 +1,4 @@
-import { BoardState, Game, PointState, Analysis } from "@/Game"
+import { BoardState, Game, PointState, Analysis, Group } from "@/Game"
 import css from "./css/IpFish.module.css"
 import blackPiece from "./svg/Go_b.svg"
 import whitePiece from "./svg/Go_w.svg"
@@ -8,11 +8,14 @@ interface GoPointProps {
   pointState: PointState,
   evaluation: number,
   bestMove: boolean,
+  // True if the stone here is in a chain that might not live.
+  weak: boolean,
   updateBoardState : (boardState: BoardState) => void,
   gameClass : Game
   row : number
   column : number
   updateAnalysisState : (analysisState: Analysis) => void
+  updateGroups : (groups: Group[]) => void
 }
 
 function formatEvaluation(evaluation : number) : string {
@@ -39,11 +42,16 @@ function getEvaluationClass(evaluation : number, bestMove : boolean) : string {
 }
 
 function GoPoint(props : GoPointProps) {
+  const pieceClass = props.weak ? `${css.goPiece} ${css.weakPiece}` : css.goPiece
+  const onClick = async () => {
+    await props.gameClass.makeMove(props.row, props.column, props.updateBoardState, props.updateAnalysisState)
+    props.updateGroups(await props.gameClass.groupStatus())
+  }
   return (
     <>
-      <td className={`${css.point}`} onClick={() => void props.gameClass.makeMove(props.row, props.column, props.updateBoardState, props.updateAnalysisState)} > 
-        <img src={blackPiece} className={css.goPiece} style={{ display: props.pointState === PointState.Black ? "block" : "none"}} />
-        <img src={whitePiece} className={css.goPiece} style={{ display: props.pointState === PointState.White ? "block" : "none"}} />
+      <td className={`${css.point}`} onClick={() => void onClick()} > 
+        <img src={blackPiece} className={pieceClass} style={{ display: props.pointState === PointState.Black ? "block" : "none"}} />
+        <img src={whitePiece} className={pieceClass} style={{ display: props.pointState === PointState.White ? "block" : "none"}} />
         <img src={offlinePoint} className={css.goPiece} style={{ display: props.pointState === PointState.Offline ? "block" : "none"}} />
         <div className={getEvaluationClass(props.evaluation, props.bestMove)} style={{ display: props.pointState === PointState.Empty ? "block" : "none"}} >
           <div className={css.evaluationText}>{formatEvaluation(props.evaluation)}</div>
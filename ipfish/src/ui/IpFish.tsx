@@ -1,4 +1,4 @@
-import { BoardState, Game, Analysis } from "@/Game"
+import { BoardState, Game, Analysis, Group } from "@/Game"
 import cssInline from "./css/IpFish.module.css?inline"
 import GoBoard from "./GoBoard"
 import { CurrentTurn } from "@/getCurrentTurn"
@@ -9,6 +9,7 @@ interface IpfishProps {
   komi : number
   initialTurn : CurrentTurn
   initalAnalysisState : Analysis
+  initialGroups : Group[]
 }
 
 function IpFish(props : IpfishProps) {
@@ -17,6 +18,8 @@ function IpFish(props : IpfishProps) {
    
   const [analysisState, updateAnalysisState] = React.useState(props.initalAnalysisState);
 
+  const [groups, updateGroups] = React.useState(props.initialGroups);
+
   return (
     <>
       <style>{cssInline}</style>
@@ -28,6 +31,8 @@ function IpFish(props : IpfishProps) {
                updateBoardState={updateBoardState}
                analysisState={analysisState}
                updateAnalysisState={updateAnalysisState}
+               groups={groups}
+               updateGroups={updateGroups}
                gameClass={props.game}
                boardSize={props.game.boardSize}
       />
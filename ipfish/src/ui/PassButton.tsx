@@ -1,4 +1,4 @@
-import { BoardState, Game, Analysis } from "@/Game"
+import { BoardState, Game, Analysis, Group } from "@/Game"
 import css from "./css/IpFish.module.css"
 
 interface PassButtonProps {
@@ -7,6 +7,7 @@ interface PassButtonProps {
   bestMove : boolean
   updateBoardState : (boardState: BoardState) => void,
   updateAnalysisState : (analysisState: Analysis) => void
+  updateGroups : (groups: Group[]) => void
 }
 
 function getEvaluationClass(evaluation : number, bestMove : boolean) : string {
@@ -20,9 +21,13 @@ function getEvaluationClass(evaluation : number, bestMove : boolean) : string {
 }
  
 function PassButton(props : PassButtonProps) {
+  const onClick = async () => {
+    await props.gameClass.passTurn(props.updateBoardState, props.updateAnalysisState)
+    props.updateGroups(await props.gameClass.groupStatus())
+  }
   return (
     <>
-      <button className={getEvaluationClass(props.evaluation, props.bestMove)} type="button" onClick={() => void props.gameClass.passTurn(props.updateBoardState, props.updateAnalysisState)}>
+      <button className={getEvaluationClass(props.evaluation, props.bestMove)} type="button" onClick={() => void onClick()}>
         Pass
         <br />
         {props.evaluation === Number.NEGATIVE_INFINITY ? "-inf" : props.evaluation.toFixed(2)}
@@ -1,14 +1,112 @@
-import { AnalaysisBoard, Analysis } from "@/Game"
-import { get_analysis } from "@rust"
+import { WorkerRequest, WorkerResponse, Analysis, AnalaysisBoard, Group } from "@/Game"
+import { Engine, EngineConfig, LifeStatus, get_group_status } from "@rust"
 
-onmessage = (event : MessageEvent<AnalaysisBoard>) => {
-  postMessage(getAnalysis(event.data))
+// The number of MCTS iterations to search between progress updates. Each one plays out mcts_simulations games.
+const STEP_ITERATIONS = 100
+// The share of the budget spent searching in steps while sending progress. The rest goes to the final analysis,
+// which may pick a different strategy than the tree search.
+const STEPPING_SHARE = 0.5
+
+// The game being played, the engine keeps its search tree between moves.
+let engine : Engine | undefined
+// Increases every time a search is started or cancelled, so a search can tell it's no longer wanted.
+let searchId = 0
+
+onmessage = (event : MessageEvent<WorkerRequest>) => {
+  const request = event.data
+  try {
+    handleRequest(request)
+  } catch (e) {
+    respondError(e, "id" in request ? request.id : undefined)
+  }
 }
 postMessage("initalized")
 
-export function getAnalysis(analysisBoard: AnalaysisBoard) : Analysis {
-  // TODO make good
-  const analysis = get_analysis(analysisBoard.boardHistory, analysisBoard.komi, analysisBoard.turn, analysisBoard.passed)
+function handleRequest(request : WorkerRequest) {
+  switch (request.type) {
+    case "newGame": {
+      stopSearch()
+      engine?.free()
+      // If the new board doesn't make sense there's no game, rather than the old one.
+      engine = undefined
+      const board = request.board
+      // The config's fields are setters, so this only changes the settings that were given.
+      const config = new EngineConfig()
+      Object.assign(config, request.settings)
+      try {
+        engine = new Engine(board.boardHistory, board.komi, board.turn, board.passed, config)
+      } finally {
+        config.free()
+      }
+      break
+    }
+    case "ourMove":
+      stopSearch()
+      getEngine().play_our_move(request.point)
+      break
+    case "opponentMove":
+      stopSearch()
+      getEngine().play_opponent_move(request.point)
+      break
+    case "analysis":
+      analyze(request.id, request.budget).catch((e) => respondError(e, request.id))
+      break
+    case "groupStatus":
+      respond({ type: "groupStatus", id: request.id, groups: getGroups(request.board, request.budget) })
+      break
+    case "sgf":
+      respond({ type: "sgf", id: request.id, sgf: getEngine().sgf() })
+      break
+  }
+}
+
+function getEngine() : Engine {
+  if (engine === undefined) {
+    throw new Error("No game has been started")
+  }
+  return engine
+}
+
+function stopSearch() {
+  searchId++
+  engine?.stop()
+}
+
+function respond(response : WorkerResponse) {
+  postMessage(response)
+}
+
+// The engine throws errors saying what's wrong with the board or move it was given. Passing them on keeps the worker going.
+// The id is the failed request's, if it has one.
+function respondError(e : unknown, id : number | undefined) {
+  respond({ type: "error", id: id, message: e instanceof Error ? e.message : String(e) })
+}
+
+async function analyze(requestId : number, budget : number) {
+  searchId++
+  const id = searchId
+  const current = getEngine()
+  const start = performance.now()
+
+  while (performance.now() - start < budget * STEPPING_SHARE) {
+    if (!current.step(STEP_ITERATIONS)) {
+      respond({ type: "cancelled", id: requestId })
+      return
+    }
+    respond({ type: "progress", id: requestId, analysis: getAnalysis(current.current_analysis(), current.ownership(), current.score_leads()) })
+    // Let other messages in. If the game moved on in the meantime, this search is no longer wanted.
+    await new Promise((resolve) => setTimeout(resolve, 0))
+    if (id !== searchId) {
+      respond({ type: "cancelled", id: requestId })
+      return
+    }
+  }
+
+  const remaining = Math.max(budget - (performance.now() - start), 0)
+  respond({ type: "analysis", id: requestId, analysis: getAnalysis(current.analysis(remaining), current.ownership(), current.score_leads()) })
+}
+
+export function getAnalysis(analysis: Float64Array, ownership: Float64Array, scoreLeads: Float64Array) : Analysis {
   // Last element represents passing, if all moves tie with passing we should pass.
   let bestMove = analysis.length - 1
   let bestScore = analysis[bestMove]
@@ -24,6 +122,24 @@ export function getAnalysis(analysisBoard: AnalaysisBoard) : Analysis {
 
   return {
     analysis: analysis,
-    bestMove: bestMove
+    bestMove: bestMove,
+    ownership: ownership,
+    scoreLeads: scoreLeads,
   }
 }
+
+// The wasm objects can't be posted back, so the groups are copied into plain objects.
+function getGroups(board : AnalaysisBoard, budget : number) : Group[] {
+  return get_group_status(board.boardHistory, board.komi, board.turn, board.passed, budget).map((group) => {
+    const result : Group = {
+      color: group.color,
+      stones: Array.from(group.stones),
+      liberties: Array.from(group.liberties),
+      inAtari: group.in_atari,
+      status: LifeStatus[group.status] as keyof typeof LifeStatus,
+      ownership: group.ownership,
+    }
+    group.free()
+    return result
+  })
+}